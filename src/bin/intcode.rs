@@ -0,0 +1,173 @@
+use advent_of_code_2019_rust::intcode::{Output, Program};
+use std::collections::VecDeque;
+use std::error::Error;
+use std::io::{self, BufRead, Write};
+
+const USAGE: &str = "usage: intcode <program> [--ascii] [--input 1,2,3] [--patch addr=value]...";
+
+#[derive(Debug, Default, PartialEq)]
+struct Options {
+    path: String,
+    ascii: bool,
+    inputs: VecDeque<i64>,
+    patches: Vec<(usize, i64)>,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let options = parse_args(std::env::args().skip(1))?;
+
+    let input = std::fs::read_to_string(&options.path)?;
+    let mut instructions = parse_instructions(&input)?;
+    for &(address, value) in &options.patches {
+        if address >= instructions.len() {
+            instructions.resize(address + 1, 0);
+        }
+        instructions[address] = value;
+    }
+
+    let mut program = Program::new(instructions);
+    let mut inputs = options.inputs;
+    let mut input = None;
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+
+    loop {
+        match program.run(input.take())? {
+            Output::Value(value) => print_value(&mut stdout, value, options.ascii)?,
+            Output::WaitingForInput => {
+                if inputs.is_empty() {
+                    inputs = prompt(&stdin, &mut stdout, options.ascii)?;
+                }
+                input = inputs.pop_front();
+            }
+            Output::Halted => break,
+        }
+    }
+    stdout.flush()?;
+
+    Ok(())
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, Box<dyn Error>> {
+    let mut options = Options::default();
+    let mut path = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--ascii" => options.ascii = true,
+            "--input" => {
+                let inputs = args.next().ok_or(USAGE)?;
+                for value in inputs.split(',') {
+                    options.inputs.push_back(value.trim().parse()?);
+                }
+            }
+            "--patch" => {
+                let patch = args.next().ok_or(USAGE)?;
+                let mut parts = patch.splitn(2, '=');
+                let address = parts.next().ok_or(USAGE)?.trim().parse()?;
+                let value = parts.next().ok_or(USAGE)?.trim().parse()?;
+                options.patches.push((address, value));
+            }
+            _ if path.is_none() && !arg.starts_with("--") => path = Some(arg),
+            _ => Err(format!("unexpected argument: {}\n{}", arg, USAGE))?,
+        }
+    }
+
+    options.path = path.ok_or(USAGE)?;
+    Ok(options)
+}
+
+fn parse_instructions(input: &str) -> Result<Vec<i64>, Box<dyn Error>> {
+    let instructions = input
+        .split(',')
+        .map(|x| x.trim().parse())
+        .collect::<Result<Vec<i64>, _>>()?;
+    Ok(instructions)
+}
+
+fn print_value(stdout: &mut impl Write, value: i64, ascii: bool) -> io::Result<()> {
+    if ascii && (0..128).contains(&value) {
+        write!(stdout, "{}", value as u8 as char)
+    } else {
+        writeln!(stdout, "{}", value)
+    }
+}
+
+fn prompt(
+    stdin: &io::Stdin,
+    stdout: &mut impl Write,
+    ascii: bool,
+) -> Result<VecDeque<i64>, Box<dyn Error>> {
+    write!(stdout, "> ")?;
+    stdout.flush()?;
+
+    let mut line = String::new();
+    if stdin.lock().read_line(&mut line)? == 0 {
+        Err("end of input while program is waiting for input")?
+    }
+
+    if ascii {
+        let line = line.trim_end_matches(&['\r', '\n'][..]);
+        Ok(line.bytes().chain(Some(b'\n')).map(i64::from).collect())
+    } else {
+        Ok(line
+            .trim()
+            .split(',')
+            .map(|x| x.trim().parse())
+            .collect::<Result<_, _>>()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter()
+            .map(|arg| arg.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let options = parse_args(args(&[
+            "day02/input.txt",
+            "--patch",
+            "1=12",
+            "--input",
+            "1,2,3",
+            "--patch",
+            "2=2",
+            "--ascii",
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            options,
+            Options {
+                path: "day02/input.txt".to_string(),
+                ascii: true,
+                inputs: vec![1, 2, 3].into_iter().collect(),
+                patches: vec![(1, 12), (2, 2)],
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_args_errors() {
+        assert!(parse_args(args(&[])).is_err());
+        assert!(parse_args(args(&["a.txt", "--patch", "1"])).is_err());
+        assert!(parse_args(args(&["a.txt", "--input"])).is_err());
+        assert!(parse_args(args(&["a.txt", "b.txt"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_instructions() {
+        assert_eq!(
+            parse_instructions("1,0,0,3,99\n").unwrap(),
+            vec![1, 0, 0, 3, 99]
+        );
+        assert!(parse_instructions("1,x").is_err());
+    }
+}
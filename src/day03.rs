@@ -1,82 +1,39 @@
+use crate::export::{self, Rgb};
+use crate::geometry::{BoundingBox, Direction, Point};
+use std::collections::BTreeMap;
 use std::error::Error;
+use std::fmt::{self, Write};
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
-struct Coord {
-    x: i32,
-    y: i32,
-}
-
-impl std::convert::From<Direction> for Coord {
-    fn from(direction: Direction) -> Self {
-        match direction {
-            Direction::Up => Coord { x: 0, y: 1 },
-            Direction::Down => Coord { x: 0, y: -1 },
-            Direction::Left => Coord { x: -1, y: 0 },
-            Direction::Right => Coord { x: 1, y: 0 },
-        }
-    }
-}
+type Coord = Point<i32>;
 
-impl std::ops::Mul<i32> for Coord {
-    type Output = Coord;
-    fn mul(self, rhs: i32) -> Self::Output {
-        Coord {
-            x: self.x * rhs,
-            y: self.y * rhs,
-        }
-    }
-}
-
-impl std::ops::Add<Coord> for Coord {
-    type Output = Coord;
-    fn add(self, rhs: Coord) -> Self::Output {
-        Coord {
-            x: self.x + rhs.x,
-            y: self.y + rhs.y,
-        }
-    }
-}
-
-#[derive(Debug, Clone, Copy)]
-enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
-}
-
-impl std::str::FromStr for Direction {
-    type Err = &'static str;
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "U" => Ok(Direction::Up),
-            "D" => Ok(Direction::Down),
-            "L" => Ok(Direction::Left),
-            "R" => Ok(Direction::Right),
-            _ => Err("invalid direction"),
-        }
-    }
-}
-
-trait IsBetween<T> {
-    fn is_between(self, a: T, b: T) -> bool;
-}
-impl IsBetween<i32> for i32 {
-    fn is_between(self, a: i32, b: i32) -> bool {
-        (a < self && self < b) || (a > self && self > b)
+fn parse_direction(s: &str) -> Result<Direction, &'static str> {
+    match s {
+        "U" => Ok(Direction::Up),
+        "D" => Ok(Direction::Down),
+        "L" => Ok(Direction::Left),
+        "R" => Ok(Direction::Right),
+        _ => Err("invalid direction"),
     }
 }
 
 struct Wire(Vec<Line>);
 impl Wire {
     pub fn coords_iter(&self) -> impl Iterator<Item = Coord> + '_ {
-        let starting_coord_iter = self
-            .0
-            .first()
-            .into_iter()
-            .flat_map(|line| line.coords_iter());
+        self.0.iter().flat_map(|line| line.coords_iter())
+    }
 
-        starting_coord_iter.chain(self.0.iter().flat_map(|line| line.coords_iter()))
+    pub fn segments(&self) -> impl Iterator<Item = (usize, &Line)> + '_ {
+        self.0.iter().scan(0, |steps, line| {
+            let start = *steps;
+            *steps += usize::from(line.distance);
+            Some((start, line))
+        })
+    }
+
+    pub fn steps_to(&self, point: Coord) -> Option<usize> {
+        self.segments()
+            .find(|(_, line)| line.bounds().contains(point))
+            .map(|(steps, line)| steps + line.steps_to(point))
     }
 }
 
@@ -88,18 +45,22 @@ struct Line {
 }
 impl Line {
     pub fn coords_iter(&self) -> impl Iterator<Item = Coord> {
-        let to_add = Coord::from(self.direction);
+        let to_add: Coord = self.direction.offset();
         let mut coord = self.start;
 
         std::iter::repeat_with(move || {
-            coord = coord + to_add;
+            coord += to_add;
             coord
         })
         .take(usize::from(self.distance))
     }
 
+    pub fn steps_to(&self, point: Coord) -> usize {
+        self.start.manhattan_distance(point) as usize
+    }
+
     pub fn end(&self) -> Coord {
-        self.start + Coord::from(self.direction) * i32::from(self.distance)
+        self.start + self.direction * i32::from(self.distance)
     }
 
     fn new(start: Coord, direction: Direction, distance: u16) -> Self {
@@ -109,117 +70,538 @@ impl Line {
             distance,
         }
     }
-    fn intersection(&self, other: &Line) -> Option<Coord> {
-        if self.is_vertical()
-            && other.is_horizontal()
-            && self.start.x.is_between(other.start.x, other.end().x)
-            && other.start.y.is_between(self.start.y, self.end().y)
-        {
-            Some(Coord {
-                x: self.start.x,
-                y: other.start.y,
-            })
-        } else if self.is_horizontal()
-            && other.is_vertical()
-            && self.start.y.is_between(other.start.y, other.end().y)
-            && other.start.x.is_between(self.start.x, self.end().x)
-        {
-            Some(Coord {
-                x: other.start.x,
-                y: self.start.y,
-            })
-        } else {
-            None
-        }
+    fn bounds(&self) -> BoundingBox<i32> {
+        let mut bounds = BoundingBox::from_point(self.start);
+        bounds.include(self.end());
+        bounds
+    }
+
+    // Axis-aligned segments share exactly the points in the overlap of their
+    // bounding boxes, which covers crossings, touching ends and collinear runs.
+    fn intersection(&self, other: &Line) -> impl Iterator<Item = Coord> {
+        self.bounds()
+            .intersection(&other.bounds())
+            .into_iter()
+            .flat_map(|shared| shared.rows().flatten())
     }
 
     fn is_horizontal(&self) -> bool {
-        match self.direction {
-            Direction::Left | Direction::Right => true,
-            Direction::Up | Direction::Down => false,
-        }
+        self.direction.is_horizontal()
     }
     fn is_vertical(&self) -> bool {
-        !self.is_horizontal()
+        self.direction.is_vertical()
     }
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let input = std::fs::read_to_string("day03/input.txt")?;
-    let mut input_lines = input.lines();
+fn parse_wire(s: &str) -> Result<Wire, Box<dyn Error>> {
+    let mut start = Coord::origin();
+    let mut lines = Vec::new();
+    for step in s.trim().split(',') {
+        let mut chars = step.chars();
+        let direction = chars.next().ok_or("empty wire step")?;
+        let direction = parse_direction(&direction.to_string())?;
+        let distance = chars
+            .as_str()
+            .parse()
+            .map_err(|e| format!("invalid distance in {:?}: {}", step, e))?;
 
-    let wire1: &str = input_lines.next().unwrap();
-    let wire1_lines: Vec<&str> = wire1.split(",").collect();
-    let wire1_lines = wire1_lines
-        .iter()
-        .map(|line| {
-            let (direction, distance) = line.split_at(1);
-            let direction: Direction = direction.parse().unwrap();
-            let distance: u16 = distance.parse().unwrap();
-            (direction, distance)
-        })
-        .scan(Coord { x: 0, y: 0 }, |from_point, (dir, dist)| {
-            let line = Line::new(*from_point, dir, dist);
-            *from_point = line.end();
-            Some(line)
-        })
-        .collect::<Vec<_>>();
+        let line = Line::new(start, direction, distance);
+        start = line.end();
+        lines.push(line);
+    }
+    Ok(Wire(lines))
+}
 
-    let wire2: &str = input_lines.next().unwrap();
-    let wire2_lines: Vec<&str> = wire2.split(",").collect();
-    let wire2_lines = wire2_lines
-        .iter()
-        .map(|line| {
-            let (direction, distance) = line.split_at(1);
-            let direction: Direction = direction.parse().unwrap();
-            let distance: u16 = distance.parse().unwrap();
-            (direction, distance)
-        })
-        .scan(Coord { x: 0, y: 0 }, |from_point, (dir, dist)| {
-            let line = Line::new(*from_point, dir, dist);
-            *from_point = line.end();
-            Some(line)
-        })
-        .collect::<Vec<_>>();
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+struct Visit {
+    wire: usize,
+    steps: usize,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Crossing {
+    point: Coord,
+    visits: Vec<Visit>,
+}
+
+impl Crossing {
+    fn total_steps(&self) -> usize {
+        self.visits.iter().map(|visit| visit.steps).sum()
+    }
+}
+
+struct Segment {
+    wire: usize,
+    line: Line,
+    steps: usize,
+}
+
+impl Segment {
+    fn steps_to(&self, point: Coord) -> usize {
+        self.steps + self.line.steps_to(point)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Origin {
+    Include,
+    Exclude,
+}
+
+// Horizontal segments join the sweep before verticals at the same x are
+// checked and leave after, so segments that only touch are still paired.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+enum Event {
+    Enter,
+    Vertical,
+    Leave,
+}
+
+fn perpendicular_pairs(segments: &[Segment]) -> Vec<(usize, usize)> {
+    let mut events = Vec::new();
+    for (i, segment) in segments.iter().enumerate() {
+        let bounds = segment.line.bounds();
+        if segment.line.is_horizontal() {
+            events.push((bounds.min.x, Event::Enter, i));
+            events.push((bounds.max.x, Event::Leave, i));
+        } else {
+            events.push((bounds.min.x, Event::Vertical, i));
+        }
+    }
+    events.sort_unstable();
 
-    let mut intersections = std::collections::HashSet::new();
-    for line1 in wire1_lines.clone() {
-        for line2 in &wire2_lines {
-            if let Some(coord) = line1.intersection(line2) {
-                intersections.insert(coord);
+    let mut pairs = Vec::new();
+    let mut active: BTreeMap<i32, Vec<usize>> = BTreeMap::new();
+    for (_, event, i) in events {
+        let bounds = segments[i].line.bounds();
+        match event {
+            Event::Enter => active.entry(bounds.min.y).or_default().push(i),
+            Event::Leave => {
+                if let Some(row) = active.get_mut(&bounds.min.y) {
+                    row.retain(|&j| j != i);
+                }
+            }
+            Event::Vertical => {
+                let rows = active.range(bounds.min.y..=bounds.max.y);
+                pairs.extend(rows.flat_map(|(_, row)| row).map(|&j| (i, j)));
             }
         }
     }
+    pairs
+}
 
-    let closest = intersections
-        .iter()
-        .min_by(|a, b| (a.x.abs() + a.y.abs()).cmp(&(b.x.abs() + b.y.abs())))
-        .unwrap();
+fn collinear_pairs(segments: &[Segment]) -> Vec<(usize, usize)> {
+    // (is horizontal, fixed coordinate) -> (low end, high end, segment)
+    let mut groups: BTreeMap<_, Vec<(i32, i32, usize)>> = BTreeMap::new();
+    for (i, segment) in segments.iter().enumerate() {
+        let bounds = segment.line.bounds();
+        let (key, lo, hi) = if segment.line.is_horizontal() {
+            ((true, bounds.min.y), bounds.min.x, bounds.max.x)
+        } else {
+            ((false, bounds.min.x), bounds.min.y, bounds.max.y)
+        };
+        groups.entry(key).or_default().push((lo, hi, i));
+    }
+
+    let mut pairs = Vec::new();
+    for mut group in groups.into_values() {
+        group.sort_unstable();
+        let mut active: Vec<(i32, usize)> = Vec::new();
+        for (lo, hi, i) in group {
+            active.retain(|&(end, _)| end >= lo);
+            pairs.extend(active.iter().map(|&(_, j)| (i, j)));
+            active.push((hi, i));
+        }
+    }
+    pairs
+}
 
-    println!("part1 ans: {}", closest.x.abs() + closest.y.abs());
+fn crossings(wires: &[Wire], origin: Origin) -> Vec<Crossing> {
+    let mut segments = Vec::new();
+    for (wire, lines) in wires.iter().enumerate() {
+        for (steps, &line) in lines.segments() {
+            segments.push(Segment { wire, line, steps });
+        }
+    }
 
-    use std::collections::HashMap;
-    let mut wire1_steps: HashMap<Coord, usize> = HashMap::new();
-    for (steps, coords) in Wire(wire1_lines.clone()).coords_iter().enumerate() {
-        if intersections.contains(&coords) {
-            wire1_steps.insert(coords, steps);
+    let mut hits: BTreeMap<Coord, BTreeMap<usize, usize>> = BTreeMap::new();
+    let pairs = perpendicular_pairs(&segments)
+        .into_iter()
+        .chain(collinear_pairs(&segments));
+    for (i, j) in pairs {
+        let (a, b) = (&segments[i], &segments[j]);
+        if a.wire == b.wire {
+            continue;
+        }
+        for point in a.line.intersection(&b.line) {
+            let visits = hits.entry(point).or_default();
+            for segment in &[a, b] {
+                let steps = segment.steps_to(point);
+                let first = visits.entry(segment.wire).or_insert(steps);
+                *first = (*first).min(steps);
+            }
         }
     }
-    let mut wire2_steps: HashMap<Coord, usize> = HashMap::new();
-    for (steps, coords) in Wire(wire2_lines.clone()).coords_iter().enumerate() {
-        if intersections.contains(&coords) {
-            wire2_steps.insert(coords, steps);
+
+    if origin == Origin::Exclude {
+        hits.remove(&Coord::origin());
+    }
+
+    hits.into_iter()
+        .map(|(point, visits)| Crossing {
+            point,
+            visits: visits
+                .into_iter()
+                .map(|(wire, steps)| Visit { wire, steps })
+                .collect(),
+        })
+        .collect()
+}
+
+const WIRE_COLOURS: [Rgb; 6] = [
+    Rgb(230, 60, 50),
+    Rgb(0, 120, 255),
+    Rgb(60, 180, 75),
+    Rgb(145, 30, 180),
+    Rgb(255, 150, 0),
+    Rgb(0, 170, 170),
+];
+
+fn render_svg(
+    out: &mut impl Write,
+    wires: &[Wire],
+    crossings: &[Crossing],
+    closest: Option<&Crossing>,
+    fewest_steps: Option<&Crossing>,
+) -> fmt::Result {
+    let mut bounds = BoundingBox::from_point(Coord::origin());
+    for Wire(lines) in wires {
+        for line in lines {
+            bounds.include(line.end());
         }
     }
 
-    let mut values = vec![];
-    for (key, value) in wire1_steps {
-        if let Some(other_value) = wire2_steps.get(&key) {
-            values.push(value + other_value)
+    // Coordinates can be tens of thousands apart, so marker sizes follow the
+    // extent of the drawing and strokes keep a fixed on-screen width.
+    let extent = bounds.width().max(bounds.height());
+    let radius = (extent / 250).max(1);
+    let margin = radius * 4;
+    let (min_x, min_y) = (bounds.min.x - margin, bounds.min.y - margin);
+    let (width, height) = (bounds.width() + 2 * margin, bounds.height() + 2 * margin);
+    let pixels: i64 = 1000;
+    let (pixel_width, pixel_height) = if width >= height {
+        (
+            pixels,
+            (pixels * i64::from(height) / i64::from(width)).max(1),
+        )
+    } else {
+        (
+            (pixels * i64::from(width) / i64::from(height)).max(1),
+            pixels,
+        )
+    };
+
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="{} {} {} {}">"#,
+        pixel_width, pixel_height, min_x, min_y, width, height
+    )?;
+    writeln!(
+        out,
+        r##"<rect x="{}" y="{}" width="{}" height="{}" fill="#ffffff"/>"##,
+        min_x, min_y, width, height
+    )?;
+
+    for (i, Wire(lines)) in wires.iter().enumerate() {
+        let points = lines
+            .first()
+            .map(|line| line.start)
+            .into_iter()
+            .chain(lines.iter().map(Line::end))
+            .map(|point| format!("{},{}", point.x, point.y))
+            .collect::<Vec<_>>();
+        writeln!(
+            out,
+            r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="1.5" stroke-linejoin="round" vector-effect="non-scaling-stroke"><title>wire {}</title></polyline>"#,
+            points.join(" "),
+            WIRE_COLOURS[i % WIRE_COLOURS.len()].hex(),
+            i + 1
+        )?;
+    }
+
+    for crossing in crossings {
+        let Point { x, y } = crossing.point;
+        writeln!(
+            out,
+            r##"<circle cx="{}" cy="{}" r="{}" fill="#000000"><title>{},{}: {} steps</title></circle>"##,
+            x,
+            y,
+            radius,
+            x,
+            y,
+            crossing.total_steps()
+        )?;
+    }
+
+    let highlights = [
+        (closest, "#ff0000", "closest"),
+        (fewest_steps, "#00a000", "fewest steps"),
+    ];
+    for &(crossing, colour, label) in highlights.iter() {
+        if let Some(crossing) = crossing {
+            writeln!(
+                out,
+                r#"<circle cx="{}" cy="{}" r="{}" fill="none" stroke="{}" stroke-width="3" vector-effect="non-scaling-stroke"><title>{}</title></circle>"#,
+                crossing.point.x,
+                crossing.point.y,
+                radius * 3,
+                colour,
+                label
+            )?;
         }
     }
 
-    println!("part2 ans: {}", values.iter().min().unwrap());
+    writeln!(
+        out,
+        r##"<rect x="{}" y="{}" width="{}" height="{}" fill="#000000"><title>origin</title></rect>"##,
+        -radius * 2,
+        -radius * 2,
+        radius * 4,
+        radius * 4
+    )?;
+    writeln!(out, "</svg>")
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let input = std::fs::read_to_string("day03/input.txt")?;
+    let wires = input
+        .lines()
+        .map(parse_wire)
+        .collect::<Result<Vec<_>, _>>()?;
+    let crossings = crossings(&wires, Origin::Exclude);
+
+    let closest = crossings
+        .iter()
+        .min_by_key(|crossing| crossing.point.manhattan_length());
+    let fewest_steps = crossings
+        .iter()
+        .min_by_key(|crossing| crossing.total_steps());
+
+    let closest_distance = closest
+        .map(|crossing| crossing.point.manhattan_length())
+        .ok_or("wires never cross")?;
+    println!("part1 ans: {}", closest_distance);
+
+    let total_steps = fewest_steps
+        .map(|crossing| crossing.total_steps())
+        .ok_or("wires never cross")?;
+    println!("part2 ans: {}", total_steps);
+
+    if let Some(path) = export::export_path("day03_wires.svg")? {
+        let mut svg = String::new();
+        render_svg(&mut svg, &wires, &crossings, closest, fewest_steps)?;
+        std::fs::write(path, svg)?;
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rng::XorShift;
+
+    fn solve(input: &str) -> (i32, usize) {
+        let wires = input
+            .lines()
+            .map(parse_wire)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let crossings = crossings(&wires, Origin::Exclude);
+        let closest = crossings.iter().map(|c| c.point.manhattan_length()).min();
+        let fewest_steps = crossings.iter().map(Crossing::total_steps).min();
+        (closest.unwrap(), fewest_steps.unwrap())
+    }
+
+    #[test]
+    fn test_examples() {
+        assert_eq!(solve("R8,U5,L5,D3\nU7,R6,D4,L4"), (6, 30));
+        assert_eq!(
+            solve("R75,D30,R83,U83,L12,D49,R71,U7,L72\nU62,R66,U55,R34,D71,R55,D58,R83"),
+            (159, 610)
+        );
+        assert_eq!(
+            solve(
+                "R98,U47,R26,D63,R33,U87,L62,D20,R33,U53,R51\nU98,R91,D20,R16,D67,R40,U7,R15,U6,R7"
+            ),
+            (135, 410)
+        );
+    }
+
+    fn parse_wires(wires: &[&str]) -> Vec<Wire> {
+        wires
+            .iter()
+            .map(|wire| parse_wire(wire))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap()
+    }
+
+    fn visits(steps: &[(usize, usize)]) -> Vec<Visit> {
+        steps
+            .iter()
+            .map(|&(wire, steps)| Visit { wire, steps })
+            .collect()
+    }
+
+    #[test]
+    fn test_overlaps_and_touches() {
+        let wires = parse_wires(&["R6", "U1,R2,D1,R3", "D2,R4,U2"]);
+
+        let with_origin = crossings(&wires, Origin::Include);
+        let without_origin = crossings(&wires, Origin::Exclude);
+
+        let points = without_origin.iter().map(|c| c.point).collect::<Vec<_>>();
+        assert_eq!(
+            points,
+            vec![
+                Coord::new(2, 0),
+                Coord::new(3, 0),
+                Coord::new(4, 0),
+                Coord::new(5, 0)
+            ]
+        );
+        assert_eq!(without_origin[0].visits, visits(&[(0, 2), (1, 4)]));
+        assert_eq!(without_origin[2].visits, visits(&[(0, 4), (1, 6), (2, 8)]));
+
+        assert_eq!(with_origin.len(), 5);
+        assert_eq!(with_origin[0].point, Coord::origin());
+        assert_eq!(with_origin[0].visits, visits(&[(0, 0), (1, 0), (2, 0)]));
+    }
+
+    #[test]
+    fn test_many_wires() {
+        let wires = parse_wires(&["R4", "U2,R2,D4", "D2,R2,U4", "U5"]);
+        let crossing = |x, y, steps: &[(usize, usize)]| Crossing {
+            point: Coord::new(x, y),
+            visits: visits(steps),
+        };
+
+        assert_eq!(
+            crossings(&wires, Origin::Exclude),
+            vec![
+                crossing(0, -2, &[(1, 2), (3, 2)]),
+                crossing(0, -1, &[(1, 1), (3, 1)]),
+                crossing(2, -2, &[(1, 4), (2, 8)]),
+                crossing(2, -1, &[(1, 5), (2, 7)]),
+                crossing(2, 0, &[(0, 2), (1, 6), (2, 6)]),
+                crossing(2, 1, &[(1, 7), (2, 5)]),
+                crossing(2, 2, &[(1, 8), (2, 4)]),
+            ]
+        );
+    }
+
+    fn random_wire(rng: &mut XorShift) -> String {
+        (0..=rng.below(8))
+            .map(|_| {
+                let direction = ["U", "D", "L", "R"][rng.below(4) as usize];
+                format!("{}{}", direction, rng.below(6))
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        let mut rng = XorShift(0x2019_0003);
+        for _ in 0..500 {
+            let wires = (0..2 + rng.below(3))
+                .map(|_| parse_wire(&random_wire(&mut rng)).unwrap())
+                .collect::<Vec<_>>();
+
+            let mut expected: BTreeMap<Coord, Vec<Visit>> = BTreeMap::new();
+            for (i, wire) in wires.iter().enumerate() {
+                let mut first_visits = BTreeMap::new();
+                first_visits.insert(Coord::origin(), 0);
+                for (steps, coord) in wire.coords_iter().enumerate() {
+                    first_visits.entry(coord).or_insert(steps + 1);
+                }
+                for (coord, steps) in first_visits {
+                    assert_eq!(wire.steps_to(coord), Some(steps));
+                    let visit = Visit { wire: i, steps };
+                    expected.entry(coord).or_default().push(visit);
+                }
+            }
+            expected.retain(|_, crossing| crossing.len() > 1);
+
+            let actual = crossings(&wires, Origin::Include)
+                .into_iter()
+                .map(|c| (c.point, c.visits))
+                .collect::<BTreeMap<Coord, Vec<Visit>>>();
+            assert_eq!(actual, expected);
+
+            expected.remove(&Coord::origin());
+            let without_origin = crossings(&wires, Origin::Exclude);
+            assert!(without_origin
+                .iter()
+                .map(|c| c.point)
+                .eq(expected.keys().copied()));
+        }
+    }
+
+    #[test]
+    fn test_first_visit_steps() {
+        let wire = parse_wire("R4,U2,L2,D4,L2,U2").unwrap();
+        assert_eq!(wire.steps_to(Coord::new(2, 0)), Some(2));
+        assert_eq!(wire.steps_to(Coord::new(2, -2)), Some(8));
+        assert_eq!(wire.steps_to(Coord::origin()), Some(0));
+        assert_eq!(wire.steps_to(Coord::new(5, 0)), None);
+
+        let other = parse_wire("D1,R2,U3").unwrap();
+        let crossings = crossings(&[wire, other], Origin::Exclude);
+        let at = |x, y| crossings.iter().find(|c| c.point == Coord::new(x, y));
+        assert_eq!(at(2, 0).unwrap().visits, visits(&[(0, 2), (1, 4)]));
+        assert_eq!(at(2, -2).unwrap().visits, visits(&[(0, 8), (1, 6)]));
+    }
+
+    #[test]
+    fn test_render_svg() {
+        let wires = parse_wires(&["R8,U5,L5,D3", "U7,R6,D4,L4"]);
+        let crossings = crossings(&wires, Origin::Exclude);
+
+        let mut svg = String::new();
+        render_svg(
+            &mut svg,
+            &wires,
+            &crossings,
+            crossings.first(),
+            crossings.last(),
+        )
+        .unwrap();
+
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert!(svg.contains(r#"viewBox="-4 -11 17 16""#));
+        assert!(svg.contains(r#"points="0,0 8,0 8,-5 3,-5 3,-2""#));
+        assert_eq!(svg.matches("<polyline").count(), 2);
+        assert_eq!(svg.matches("<circle").count(), crossings.len() + 2);
+    }
+
+    #[test]
+    fn test_render_svg_large_coordinates() {
+        let wires = parse_wires(&["R60000,U30000", "U10000,R20000,D20000"]);
+        let crossings = crossings(&wires, Origin::Exclude);
+
+        let mut svg = String::new();
+        render_svg(&mut svg, &wires, &crossings, None, None).unwrap();
+
+        assert!(svg.contains(r#"width="1000" height="677" viewBox="-960 -30960 61921 41921""#));
+        assert!(svg.contains(r#"r="240""#));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse_wire("R8,X5").is_err());
+        assert!(parse_wire("R8,U").is_err());
+        assert!(parse_wire("R8,,U1").is_err());
+    }
+}
@@ -1,40 +1,968 @@
 use digits_iterator::*;
 use itertools::Itertools;
 use rayon::prelude::*;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
 
-fn main() {
-    let range = || (254032..=789860u32).into_par_iter();
+const PART1_RULES: &str = "non-decreasing and run-at-least(2)";
+const PART2_RULES: &str = "non-decreasing and run-exactly(2)";
 
-    let part1_ans = range()
-        .filter(has_monotonically_increasing_digits)
-        .filter(has_double_digit)
-        .count();
+fn main() -> Result<(), Box<dyn Error>> {
+    let (lo, hi) = (254032, 789860);
+
+    let part1_ans = count_matching(&PasswordRule::parse(PART1_RULES)?, lo, hi)?;
 
     println!("{}", part1_ans);
 
-    let part2_ans = range()
-        .filter(has_monotonically_increasing_digits)
-        .filter(has_strict_double_digit)
-        .count();
+    let part2_ans = count_matching(&PasswordRule::parse(PART2_RULES)?, lo, hi)?;
 
     println!("{}", part2_ans);
+
+    Ok(())
 }
 
-fn has_double_digit(x: &u32) -> bool {
+fn has_double_digit(x: &u64) -> bool {
     digit_pairs(x).any(|(a, b)| a == b)
 }
 
-fn has_monotonically_increasing_digits(x: &u32) -> bool {
+fn has_monotonically_increasing_digits(x: &u64) -> bool {
     digit_pairs(x).all(|(a, b)| a <= b)
 }
 
-fn digit_pairs(x: &u32) -> impl Iterator<Item = (u8, u8)> {
+fn digit_pairs(x: &u64) -> impl Iterator<Item = (u8, u8)> {
     x.digits().zip(x.digits().skip(1))
 }
 
-fn has_strict_double_digit(x: &u32) -> bool {
+fn has_strict_double_digit(x: &u64) -> bool {
     x.digits()
         .group_by(|&x| x)
         .into_iter()
         .any(|(_key, grp)| grp.count() == 2)
 }
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum RunLength {
+    AtLeast(usize),
+    Exactly(usize),
+}
+
+impl RunLength {
+    // Runs longer than this all behave the same, which keeps the state small.
+    fn cap(self) -> usize {
+        match self {
+            RunLength::AtLeast(n) => n,
+            RunLength::Exactly(n) => n + 1,
+        }
+    }
+
+    fn is_satisfied_by(self, run: usize) -> bool {
+        match self {
+            RunLength::AtLeast(n) => run >= n,
+            RunLength::Exactly(n) => run == n,
+        }
+    }
+
+    fn matches(self, x: &u64) -> bool {
+        match self {
+            RunLength::AtLeast(2) => has_double_digit(x),
+            RunLength::Exactly(2) => has_strict_double_digit(x),
+            _ => x
+                .digits()
+                .group_by(|&x| x)
+                .into_iter()
+                .any(|(_key, grp)| self.is_satisfied_by(grp.count())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum PasswordRule {
+    NonDecreasing,
+    Run(RunLength),
+    DigitCount(u32, u32),
+    DigitSum(u32, u32),
+    Forbidden(Vec<u8>),
+    And(Vec<PasswordRule>),
+    Or(Vec<PasswordRule>),
+    Not(Box<PasswordRule>),
+}
+
+impl PasswordRule {
+    fn parse(input: &str) -> Result<PasswordRule, Box<dyn Error>> {
+        let mut parser = RuleParser {
+            tokens: tokenize(input)?,
+            position: 0,
+        };
+        let rule = parser.expression()?;
+        match parser.tokens.get(parser.position) {
+            Some((column, token)) => Err(format!("unexpected {} at column {}", token, column))?,
+            None => Ok(rule),
+        }
+    }
+
+    // One rule per line, all of which must hold; '#' starts a comment.
+    fn parse_rule_set(input: &str) -> Result<PasswordRule, Box<dyn Error>> {
+        let mut rules = Vec::new();
+        for (number, line) in input.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if !line.is_empty() {
+                let rule =
+                    PasswordRule::parse(line).map_err(|e| format!("line {}: {}", number + 1, e))?;
+                rules.push(rule);
+            }
+        }
+        match rules.len() {
+            0 => Err("empty rule set")?,
+            1 => Ok(rules.remove(0)),
+            _ => Ok(PasswordRule::And(rules)),
+        }
+    }
+
+    fn and(self, other: PasswordRule) -> PasswordRule {
+        match self {
+            PasswordRule::And(mut rules) => {
+                rules.push(other);
+                PasswordRule::And(rules)
+            }
+            rule => PasswordRule::And(vec![rule, other]),
+        }
+    }
+
+    fn or(self, other: PasswordRule) -> PasswordRule {
+        match self {
+            PasswordRule::Or(mut rules) => {
+                rules.push(other);
+                PasswordRule::Or(rules)
+            }
+            rule => PasswordRule::Or(vec![rule, other]),
+        }
+    }
+
+    fn matches(&self, password: u64) -> bool {
+        self.matches_digits(&password.digits().collect::<Vec<u8>>())
+    }
+
+    fn matches_digits(&self, digits: &[u8]) -> bool {
+        match self {
+            PasswordRule::NonDecreasing => digits.windows(2).all(|pair| pair[0] <= pair[1]),
+            PasswordRule::Run(run_length) => digits
+                .iter()
+                .group_by(|&&digit| digit)
+                .into_iter()
+                .any(|(_digit, run)| run_length.is_satisfied_by(run.count())),
+            PasswordRule::DigitCount(min, max) => (*min..=*max).contains(&(digits.len() as u32)),
+            PasswordRule::DigitSum(min, max) => {
+                (*min..=*max).contains(&digits.iter().map(|&digit| u32::from(digit)).sum())
+            }
+            PasswordRule::Forbidden(forbidden) => {
+                !digits.iter().any(|digit| forbidden.contains(digit))
+            }
+            PasswordRule::And(rules) => rules.iter().all(|rule| rule.matches_digits(digits)),
+            PasswordRule::Or(rules) => rules.iter().any(|rule| rule.matches_digits(digits)),
+            PasswordRule::Not(rule) => !rule.matches_digits(digits),
+        }
+    }
+
+    fn accepts(&self, facts: &Facts, tracking: &Tracking) -> bool {
+        match self {
+            PasswordRule::NonDecreasing => !facts.decreased,
+            PasswordRule::Run(run_length) => facts.runs_met & tracking.run_bit(*run_length) != 0,
+            PasswordRule::DigitCount(min, max) => (*min..=*max).contains(&facts.length),
+            PasswordRule::DigitSum(min, max) => (*min..=*max).contains(&facts.sum),
+            PasswordRule::Forbidden(forbidden) => !forbidden
+                .iter()
+                .any(|&digit| facts.used & (1 << digit) != 0),
+            PasswordRule::And(rules) => rules.iter().all(|rule| rule.accepts(facts, tracking)),
+            PasswordRule::Or(rules) => rules.iter().any(|rule| rule.accepts(facts, tracking)),
+            PasswordRule::Not(rule) => !rule.accepts(facts, tracking),
+        }
+    }
+
+    fn atoms(&self) -> Vec<&PasswordRule> {
+        match self {
+            PasswordRule::And(rules) | PasswordRule::Or(rules) => {
+                rules.iter().flat_map(PasswordRule::atoms).collect()
+            }
+            PasswordRule::Not(rule) => rule.atoms(),
+            atom => vec![atom],
+        }
+    }
+
+    fn is_compound(&self) -> bool {
+        matches!(self, PasswordRule::And(_) | PasswordRule::Or(_))
+    }
+}
+
+impl std::ops::Not for PasswordRule {
+    type Output = PasswordRule;
+    fn not(self) -> PasswordRule {
+        PasswordRule::Not(Box::new(self))
+    }
+}
+
+impl fmt::Display for PasswordRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let bounds = |f: &mut fmt::Formatter, name, min: &u32, max: &u32| {
+            if min == max {
+                write!(f, "{}({})", name, min)
+            } else {
+                write!(f, "{}({}..={})", name, min, max)
+            }
+        };
+        match self {
+            PasswordRule::NonDecreasing => write!(f, "non-decreasing"),
+            PasswordRule::Run(RunLength::Exactly(n)) => write!(f, "run-exactly({})", n),
+            PasswordRule::Run(RunLength::AtLeast(n)) => write!(f, "run-at-least({})", n),
+            PasswordRule::DigitCount(min, max) => bounds(f, "digits", min, max),
+            PasswordRule::DigitSum(min, max) => bounds(f, "digit-sum", min, max),
+            PasswordRule::Forbidden(digits) => write!(f, "forbid({})", digits.iter().join(", ")),
+            PasswordRule::And(rules) => {
+                let rules = rules.iter().map(|rule| match rule {
+                    PasswordRule::Or(_) => format!("({})", rule),
+                    _ => rule.to_string(),
+                });
+                write!(f, "{}", rules.format(" and "))
+            }
+            PasswordRule::Or(rules) => write!(f, "{}", rules.iter().format(" or ")),
+            PasswordRule::Not(rule) if rule.is_compound() => write!(f, "not ({})", rule),
+            PasswordRule::Not(rule) => write!(f, "not {}", rule),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Token {
+    Word(String),
+    Number(u32),
+    Open,
+    Close,
+    Comma,
+    Range,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Word(word) => write!(f, "'{}'", word),
+            Token::Number(n) => write!(f, "'{}'", n),
+            Token::Open => write!(f, "'('"),
+            Token::Close => write!(f, "')'"),
+            Token::Comma => write!(f, "','"),
+            Token::Range => write!(f, "'..='"),
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<(usize, Token)>, Box<dyn Error>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+    while let Some(&(i, c)) = chars.peek() {
+        let column = i + 1;
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_alphabetic() {
+            let mut word = String::new();
+            while let Some(&(_, c)) = chars
+                .peek()
+                .filter(|(_, c)| c.is_ascii_alphabetic() || *c == '-')
+            {
+                word.push(c);
+                chars.next();
+            }
+            tokens.push((column, Token::Word(word)));
+        } else if c.is_ascii_digit() {
+            let mut number = String::new();
+            while let Some(&(_, c)) = chars.peek().filter(|(_, c)| c.is_ascii_digit()) {
+                number.push(c);
+                chars.next();
+            }
+            let number = number
+                .parse()
+                .map_err(|e| format!("invalid number at column {}: {}", column, e))?;
+            tokens.push((column, Token::Number(number)));
+        } else if input[i..].starts_with("..=") {
+            tokens.push((column, Token::Range));
+            chars.nth(2);
+        } else {
+            let token = match c {
+                '(' => Token::Open,
+                ')' => Token::Close,
+                ',' => Token::Comma,
+                _ => Err(format!("unexpected character {:?} at column {}", c, column))?,
+            };
+            tokens.push((column, token));
+            chars.next();
+        }
+    }
+    Ok(tokens)
+}
+
+struct RuleParser {
+    tokens: Vec<(usize, Token)>,
+    position: usize,
+}
+
+impl RuleParser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(_, token)| token)
+    }
+
+    fn next(&mut self) -> Result<Token, Box<dyn Error>> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .ok_or("unexpected end of rule")?;
+        self.position += 1;
+        Ok(token.1.clone())
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), Box<dyn Error>> {
+        let column = self.tokens.get(self.position).map(|&(column, _)| column);
+        match self.next()? {
+            ref token if *token == expected => Ok(()),
+            token => Err(format!(
+                "expected {} but found {} at column {}",
+                expected,
+                token,
+                column.unwrap_or(0)
+            ))?,
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let found = self.peek() == Some(&Token::Word(keyword.to_string()));
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn expression(&mut self) -> Result<PasswordRule, Box<dyn Error>> {
+        let mut rule = self.term()?;
+        while self.keyword("or") {
+            rule = rule.or(self.term()?);
+        }
+        Ok(rule)
+    }
+
+    fn term(&mut self) -> Result<PasswordRule, Box<dyn Error>> {
+        let mut rule = self.factor()?;
+        while self.keyword("and") {
+            rule = rule.and(self.factor()?);
+        }
+        Ok(rule)
+    }
+
+    fn factor(&mut self) -> Result<PasswordRule, Box<dyn Error>> {
+        if self.keyword("not") {
+            return Ok(!self.factor()?);
+        }
+        if self.peek() == Some(&Token::Open) {
+            self.position += 1;
+            let rule = self.expression()?;
+            self.expect(Token::Close)?;
+            return Ok(rule);
+        }
+        self.atom()
+    }
+
+    fn number(&mut self) -> Result<u32, Box<dyn Error>> {
+        match self.next()? {
+            Token::Number(n) => Ok(n),
+            token => Err(format!("expected a number but found {}", token))?,
+        }
+    }
+
+    fn bounds(&mut self) -> Result<(u32, u32), Box<dyn Error>> {
+        self.expect(Token::Open)?;
+        let min = self.number()?;
+        let max = if self.peek() == Some(&Token::Range) {
+            self.position += 1;
+            self.number()?
+        } else {
+            min
+        };
+        self.expect(Token::Close)?;
+        Ok((min, max))
+    }
+
+    fn atom(&mut self) -> Result<PasswordRule, Box<dyn Error>> {
+        let column = self.tokens.get(self.position).map(|&(column, _)| column);
+        let name = match self.next()? {
+            Token::Word(name) => name,
+            token => Err(format!(
+                "expected a rule but found {} at column {}",
+                token,
+                column.unwrap_or(0)
+            ))?,
+        };
+
+        let rule = match name.as_str() {
+            "non-decreasing" => PasswordRule::NonDecreasing,
+            "run-exactly" | "run-at-least" => {
+                let (n, max) = self.bounds()?;
+                if n != max || n == 0 {
+                    Err(format!("{} needs a single positive length", name))?
+                }
+                let n = n as usize;
+                match name.as_str() {
+                    "run-exactly" => PasswordRule::Run(RunLength::Exactly(n)),
+                    _ => PasswordRule::Run(RunLength::AtLeast(n)),
+                }
+            }
+            "digits" => {
+                let (min, max) = self.bounds()?;
+                PasswordRule::DigitCount(min, max)
+            }
+            "digit-sum" => {
+                let (min, max) = self.bounds()?;
+                PasswordRule::DigitSum(min, max)
+            }
+            "forbid" => {
+                self.expect(Token::Open)?;
+                let mut digits = vec![self.number()?];
+                while self.peek() == Some(&Token::Comma) {
+                    self.position += 1;
+                    digits.push(self.number()?);
+                }
+                self.expect(Token::Close)?;
+                if let Some(digit) = digits.iter().find(|&&digit| digit > 9) {
+                    Err(format!("forbid takes single digits, found {}", digit))?
+                }
+                digits.sort_unstable();
+                digits.dedup();
+                PasswordRule::Forbidden(digits.into_iter().map(|digit| digit as u8).collect())
+            }
+            _ => Err(format!(
+                "unknown rule '{}' at column {}",
+                name,
+                column.unwrap_or(0)
+            ))?,
+        };
+        Ok(rule)
+    }
+}
+
+// What the counter remembers about a prefix of digits. Fields that no rule
+// looks at stay at their defaults so equivalent prefixes share a memo entry.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
+struct Facts {
+    last: Option<u8>,
+    run: usize,
+    runs_met: u64,
+    decreased: bool,
+    length: u32,
+    sum: u32,
+    used: u16,
+}
+
+// Facts::runs_met tracks which run rules have been met in one bit each.
+const MAX_RUN_RULES: usize = 64;
+
+struct Tracking {
+    runs: Vec<RunLength>,
+    run_cap: usize,
+    order: bool,
+    length_cap: u32,
+    sum_cap: u32,
+    used: bool,
+}
+
+impl Tracking {
+    fn new(rule: &PasswordRule) -> Result<Tracking, Box<dyn Error>> {
+        let mut tracking = Tracking {
+            runs: Vec::new(),
+            run_cap: 0,
+            order: false,
+            length_cap: 0,
+            sum_cap: 0,
+            used: false,
+        };
+        for atom in rule.atoms() {
+            match atom {
+                PasswordRule::NonDecreasing => tracking.order = true,
+                PasswordRule::Run(run_length) => {
+                    if !tracking.runs.contains(run_length) {
+                        tracking.runs.push(*run_length);
+                    }
+                    tracking.run_cap = tracking.run_cap.max(run_length.cap());
+                }
+                PasswordRule::DigitCount(_, max) => {
+                    tracking.length_cap = tracking.length_cap.max(max.saturating_add(1))
+                }
+                PasswordRule::DigitSum(_, max) => {
+                    tracking.sum_cap = tracking.sum_cap.max(max.saturating_add(1))
+                }
+                PasswordRule::Forbidden(_) => tracking.used = true,
+                _ => (),
+            }
+        }
+        if tracking.runs.len() > MAX_RUN_RULES {
+            Err(format!(
+                "{} different run rules, at most {} are supported",
+                tracking.runs.len(),
+                MAX_RUN_RULES
+            ))?
+        }
+        Ok(tracking)
+    }
+
+    fn run_bit(&self, run_length: RunLength) -> u64 {
+        let index = self.runs.iter().position(|&run| run == run_length);
+        index.map_or(0, |index| 1 << index)
+    }
+
+    fn runs_met_by(&self, run: usize) -> u64 {
+        self.runs
+            .iter()
+            .filter(|run_length| run_length.is_satisfied_by(run))
+            .fold(0, |bits, &run_length| bits | self.run_bit(run_length))
+    }
+
+    fn push(&self, facts: Facts, digit: u8) -> Facts {
+        let mut next = facts;
+        if self.order || !self.runs.is_empty() {
+            if facts.last == Some(digit) {
+                next.run = (facts.run + 1).min(self.run_cap);
+            } else {
+                next.runs_met |= self.runs_met_by(facts.run);
+                next.last = Some(digit);
+                next.run = 1.min(self.run_cap);
+            }
+        }
+        if self.order {
+            next.decreased |= facts.last.is_some_and(|last| digit < last);
+        }
+        next.length = (facts.length + 1).min(self.length_cap);
+        next.sum = (facts.sum + u32::from(digit)).min(self.sum_cap);
+        if self.used {
+            next.used |= 1 << digit;
+        }
+        next
+    }
+
+    fn finish(&self, facts: Facts) -> Facts {
+        Facts {
+            runs_met: facts.runs_met | self.runs_met_by(facts.run),
+            ..facts
+        }
+    }
+}
+
+struct PasswordCounter<'a> {
+    rule: &'a PasswordRule,
+    tracking: Tracking,
+    digits: Vec<u8>,
+    memo: HashMap<(usize, Facts), u64>,
+}
+
+impl<'a> PasswordCounter<'a> {
+    fn count(&mut self, position: usize, tight: bool, prefix: Option<Facts>) -> u64 {
+        if position == self.digits.len() {
+            // nothing but leading zeros is the number 0 itself
+            let facts = prefix.unwrap_or_else(|| self.tracking.push(Facts::default(), 0));
+            let facts = self.tracking.finish(facts);
+            return self.rule.accepts(&facts, &self.tracking) as u64;
+        }
+
+        let key = prefix.filter(|_| !tight).map(|facts| (position, facts));
+        if let Some(count) = key.and_then(|key| self.memo.get(&key)) {
+            return *count;
+        }
+
+        let limit = if tight { self.digits[position] } else { 9 };
+        let mut count = 0;
+        for digit in 0..=limit {
+            let next = match prefix {
+                None if digit == 0 => None,
+                None => Some(self.tracking.push(Facts::default(), digit)),
+                Some(facts) => Some(self.tracking.push(facts, digit)),
+            };
+            count += self.count(position + 1, tight && digit == limit, next);
+        }
+
+        if let Some(key) = key {
+            self.memo.insert(key, count);
+        }
+        count
+    }
+}
+
+fn count_up_to(rule: &PasswordRule, max: u64) -> Result<u64, Box<dyn Error>> {
+    let mut counter = PasswordCounter {
+        rule,
+        tracking: Tracking::new(rule)?,
+        digits: max.digits().collect(),
+        memo: HashMap::new(),
+    };
+    Ok(counter.count(0, true, None))
+}
+
+fn count_matching(rule: &PasswordRule, lo: u64, hi: u64) -> Result<u64, Box<dyn Error>> {
+    if lo > hi {
+        return Ok(0);
+    }
+    let below = match lo.checked_sub(1) {
+        Some(max) => count_up_to(rule, max)?,
+        None => 0,
+    };
+    Ok(count_up_to(rule, hi)? - below)
+}
+
+fn count_brute_force(rule: &PasswordRule, lo: u64, hi: u64) -> u64 {
+    (lo..=hi)
+        .into_par_iter()
+        .filter(|&password| rule.matches(password))
+        .count() as u64
+}
+
+fn count_passwords(lo: u64, hi: u64, run_length: RunLength) -> u64 {
+    let rule = PasswordRule::NonDecreasing.and(PasswordRule::Run(run_length));
+    count_matching(&rule, lo, hi).expect("a single run rule fits in the tracking bits")
+}
+
+// Smallest number >= x whose digits never decrease: after the first drop,
+// every remaining digit is raised to the one before it (254032 -> 255555).
+fn next_non_decreasing(x: u64) -> Option<u64> {
+    let digits = x.digits().collect::<Vec<u8>>();
+    let drop = digits.windows(2).position(|pair| pair[0] > pair[1]);
+    let digits = match drop {
+        Some(i) => {
+            let mut digits = digits;
+            let fill = digits[i];
+            digits[i + 1..].iter_mut().for_each(|digit| *digit = fill);
+            digits
+        }
+        None => return Some(x),
+    };
+    digits.iter().try_fold(0u64, |number, &digit| {
+        number.checked_mul(10)?.checked_add(u64::from(digit))
+    })
+}
+
+struct Passwords {
+    next: Option<u64>,
+    hi: u64,
+    run_length: RunLength,
+}
+
+impl Passwords {
+    fn new(lo: u64, hi: u64, run_length: RunLength) -> Passwords {
+        Passwords {
+            next: Some(lo),
+            hi,
+            run_length,
+        }
+    }
+
+    fn part1(lo: u64, hi: u64) -> Passwords {
+        Passwords::new(lo, hi, RunLength::AtLeast(2))
+    }
+
+    fn part2(lo: u64, hi: u64) -> Passwords {
+        Passwords::new(lo, hi, RunLength::Exactly(2))
+    }
+
+    fn page(self, page: usize, per_page: usize) -> Vec<u64> {
+        self.skip(page * per_page).take(per_page).collect()
+    }
+}
+
+impl Iterator for Passwords {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        loop {
+            let candidate = next_non_decreasing(self.next?).filter(|&x| x <= self.hi);
+            self.next = candidate.and_then(|x| x.checked_add(1));
+            let candidate = candidate?;
+            if self.run_length.matches(&candidate) {
+                return Some(candidate);
+            }
+        }
+    }
+
+    // Binary search for the first number with n matches before it, using the
+    // counter instead of walking past every skipped password.
+    fn nth(&mut self, n: usize) -> Option<u64> {
+        let lo = self.next?;
+        let target = n as u64 + 1;
+        if lo > self.hi || count_passwords(lo, self.hi, self.run_length) < target {
+            self.next = None;
+            return None;
+        }
+
+        let (mut low, mut high) = (lo, self.hi);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if count_passwords(lo, mid, self.run_length) >= target {
+                high = mid;
+            } else {
+                low = mid + 1;
+            }
+        }
+        self.next = low.checked_add(1);
+        Some(low)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rng::XorShift;
+
+    fn brute_force(lo: u64, hi: u64, strict: bool) -> Vec<u64> {
+        (lo..=hi)
+            .filter(has_monotonically_increasing_digits)
+            .filter(|x| {
+                if strict {
+                    has_strict_double_digit(x)
+                } else {
+                    has_double_digit(x)
+                }
+            })
+            .collect()
+    }
+
+    fn random_rule(rng: &mut XorShift, depth: u32) -> PasswordRule {
+        let choice = if depth == 0 {
+            rng.below(6)
+        } else {
+            rng.below(9)
+        };
+        match choice {
+            0 => PasswordRule::NonDecreasing,
+            1 => PasswordRule::Run(RunLength::Exactly(1 + rng.below(3) as usize)),
+            2 => PasswordRule::Run(RunLength::AtLeast(1 + rng.below(3) as usize)),
+            3 => {
+                let min = 1 + rng.below(5) as u32;
+                PasswordRule::DigitCount(min, min + rng.below(2) as u32)
+            }
+            4 => {
+                let min = rng.below(25) as u32;
+                PasswordRule::DigitSum(min, min + rng.below(15) as u32)
+            }
+            5 => PasswordRule::Forbidden(vec![rng.below(5) as u8, 5 + rng.below(5) as u8]),
+            6 => random_rule(rng, depth - 1).and(random_rule(rng, depth - 1)),
+            7 => random_rule(rng, depth - 1).or(random_rule(rng, depth - 1)),
+            _ => !random_rule(rng, depth - 1),
+        }
+    }
+
+    #[test]
+    fn test_rule_evaluators_agree() {
+        let mut rng = XorShift(0x2019_0042);
+        for _ in 0..300 {
+            let rule = random_rule(&mut rng, 3);
+            let lo = rng.below(100_000);
+            let hi = lo + rng.below(5_000);
+            assert_eq!(
+                count_matching(&rule, lo, hi).unwrap(),
+                count_brute_force(&rule, lo, hi),
+                "{} in {}..={}",
+                rule,
+                lo,
+                hi
+            );
+
+            let reparsed = PasswordRule::parse(&rule.to_string()).unwrap();
+            assert_eq!(reparsed.to_string(), rule.to_string());
+        }
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        let rule = PasswordRule::parse(
+            "non-decreasing and (run-exactly(2) or not forbid(7, 0)) and digit-sum(10..=20)",
+        )
+        .unwrap();
+        assert_eq!(
+            rule,
+            PasswordRule::And(vec![
+                PasswordRule::NonDecreasing,
+                PasswordRule::Or(vec![
+                    PasswordRule::Run(RunLength::Exactly(2)),
+                    !PasswordRule::Forbidden(vec![0, 7]),
+                ]),
+                PasswordRule::DigitSum(10, 20),
+            ])
+        );
+        assert_eq!(
+            rule.to_string(),
+            "non-decreasing and (run-exactly(2) or not forbid(0, 7)) and digit-sum(10..=20)"
+        );
+        assert!(rule.matches(112_233));
+        assert!(!rule.matches(111_234));
+        assert!(rule.matches(111_237));
+
+        let config =
+            "# day 4, part 2\nnon-decreasing\n\nrun-exactly(2)  # a strict pair\ndigits(6)\n";
+        let rule_set = PasswordRule::parse_rule_set(config).unwrap();
+        assert_eq!(count_matching(&rule_set, 254032, 789860).unwrap(), 670);
+        assert_eq!(count_brute_force(&rule_set, 254032, 789860), 670);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |input: &str| PasswordRule::parse(input).unwrap_err().to_string();
+        assert_eq!(error("non-decreasing or"), "unexpected end of rule");
+        assert_eq!(
+            error("run-twice(2)"),
+            "unknown rule 'run-twice' at column 1"
+        );
+        assert_eq!(
+            error("digits(6) digits(7)"),
+            "unexpected 'digits' at column 11"
+        );
+        assert_eq!(
+            error("forbid(1, 12)"),
+            "forbid takes single digits, found 12"
+        );
+        assert_eq!(error("digits(6"), "unexpected end of rule");
+        assert_eq!(error("digits[6]"), "unexpected character '[' at column 7");
+
+        let error = PasswordRule::parse_rule_set("digits(6)\nrun-exactly(0)\n").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "line 2: run-exactly needs a single positive length"
+        );
+    }
+
+    #[test]
+    fn test_run_rule_limit() {
+        let runs = |n: usize| {
+            (1..=n)
+                .map(|length| format!("run-exactly({})", length))
+                .collect::<Vec<_>>()
+        };
+
+        let rule = PasswordRule::parse(&runs(64).join(" or ")).unwrap();
+        assert_eq!(count_matching(&rule, 1, 100).unwrap(), 100);
+        let rule = PasswordRule::parse(&runs(65).join(" or ")).unwrap();
+        assert_eq!(
+            count_matching(&rule, 1, 100).unwrap_err().to_string(),
+            "65 different run rules, at most 64 are supported"
+        );
+
+        let mut lines = runs(65);
+        lines[64] = "run-exactly(1)".to_string();
+        let rule_set = PasswordRule::parse_rule_set(&lines.join("\n")).unwrap();
+        assert_eq!(count_matching(&rule_set, 1, 100).unwrap(), 0);
+
+        let built = (1..=65)
+            .map(|length| PasswordRule::Run(RunLength::AtLeast(length)))
+            .fold(PasswordRule::NonDecreasing, PasswordRule::and);
+        assert!(count_matching(&built, 1, 100).is_err());
+    }
+
+    #[test]
+    fn test_puzzle_range() {
+        assert_eq!(count_passwords(254032, 789860, RunLength::AtLeast(2)), 1033);
+        assert_eq!(count_passwords(254032, 789860, RunLength::Exactly(2)), 670);
+    }
+
+    #[test]
+    fn test_matches_predicates() {
+        let mut rng = XorShift(0x2019_0004);
+        for _ in 0..200 {
+            let lo = 1 + rng.below(200_000);
+            let hi = lo + rng.below(20_000);
+            assert_eq!(
+                count_passwords(lo, hi, RunLength::AtLeast(2)),
+                brute_force(lo, hi, false).len() as u64,
+                "{}..={}",
+                lo,
+                hi
+            );
+            assert_eq!(
+                count_passwords(lo, hi, RunLength::Exactly(2)),
+                brute_force(lo, hi, true).len() as u64,
+                "{}..={}",
+                lo,
+                hi
+            );
+        }
+    }
+
+    #[test]
+    fn test_long_passwords() {
+        // a non-decreasing n-digit number is a multiset of n digits from 1-9,
+        // and with more than nine digits some digit has to repeat
+        let (twelve, eighteen) = (10u64.pow(11)..10u64.pow(12), 10u64.pow(17)..10u64.pow(18));
+        let count = |digits: &std::ops::Range<u64>, run_length| {
+            count_passwords(digits.start, digits.end - 1, run_length)
+        };
+        assert_eq!(count(&twelve, RunLength::AtLeast(1)), 125_970);
+        assert_eq!(count(&eighteen, RunLength::AtLeast(2)), 1_562_275);
+        assert_eq!(count(&twelve, RunLength::Exactly(2)), 98_088);
+        assert_eq!(count(&eighteen, RunLength::Exactly(2)), 1_223_329);
+        assert_eq!(count(&eighteen, RunLength::Exactly(3)), 1_010_736);
+
+        assert_eq!(count_passwords(0, 9, RunLength::AtLeast(2)), 0);
+        assert_eq!(count_passwords(10, 1, RunLength::AtLeast(1)), 0);
+        // only 11111111111111111111 has twenty digits and fits in a u64
+        assert_eq!(count_passwords(0, u64::MAX, RunLength::Exactly(20)), 1);
+    }
+
+    #[test]
+    fn test_next_non_decreasing() {
+        assert_eq!(next_non_decreasing(254032), Some(255555));
+        assert_eq!(next_non_decreasing(255555), Some(255555));
+        assert_eq!(next_non_decreasing(789860), Some(789999));
+        assert_eq!(next_non_decreasing(0), Some(0));
+        assert_eq!(next_non_decreasing(u64::MAX), None);
+    }
+
+    #[test]
+    fn test_enumeration() {
+        let part1 = Passwords::part1(254032, 789860).take(3).collect::<Vec<_>>();
+        assert_eq!(part1, vec![255555, 255556, 255557]);
+        let part2 = Passwords::part2(254032, 789860).take(3).collect::<Vec<_>>();
+        assert_eq!(part2, vec![255566, 255577, 255588]);
+
+        assert_eq!(Passwords::part1(254032, 789860).count(), 1033);
+        assert_eq!(Passwords::part2(254032, 789860).count(), 670);
+        assert_eq!(Passwords::part1(u64::MAX - 10, u64::MAX).next(), None);
+
+        let mut rng = XorShift(0x2019_0043);
+        for _ in 0..20 {
+            let lo = rng.below(200_000);
+            let hi = lo + rng.below(20_000);
+            for &strict in &[false, true] {
+                let expected = brute_force(lo, hi, strict);
+                let passwords = || {
+                    if strict {
+                        Passwords::part2(lo, hi)
+                    } else {
+                        Passwords::part1(lo, hi)
+                    }
+                };
+                assert_eq!(passwords().collect::<Vec<_>>(), expected);
+
+                let k = rng.below(expected.len() as u64 + 2) as usize;
+                assert_eq!(passwords().nth(k), expected.get(k).copied());
+
+                let mut passwords = passwords();
+                if passwords.nth(k).is_some() {
+                    assert_eq!(passwords.next(), expected.get(k + 1).copied());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_pagination() {
+        let all = Passwords::part2(254032, 789860).collect::<Vec<_>>();
+        let pages = (0..)
+            .map(|page| Passwords::part2(254032, 789860).page(page, 100))
+            .take_while(|page| !page.is_empty())
+            .collect::<Vec<_>>();
+        assert_eq!(pages.len(), 7);
+        assert_eq!(pages[6].len(), 70);
+        assert_eq!(pages.concat(), all);
+
+        let twelve_digits = Passwords::part2(10u64.pow(11), 10u64.pow(12) - 1);
+        assert_eq!(twelve_digits.page(98_087, 1), vec![889_999_999_999]);
+    }
+}
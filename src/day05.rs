@@ -1,4 +1,4 @@
-use digits_iterator::*;
+use crate::intcode::{Instruction, Mode, Opcode, Operand};
 use std::convert::TryFrom;
 use std::error::Error;
 
@@ -7,11 +7,11 @@ enum Parameter {
     Immediate(i32),
 }
 impl Parameter {
-    fn new(mode: u8, value: i32) -> Result<Parameter, Box<dyn Error>> {
+    fn new(mode: Mode, value: i32) -> Result<Parameter, Box<dyn Error>> {
         match mode {
-            0 => Ok(Parameter::Position(usize::try_from(value)?)),
-            1 => Ok(Parameter::Immediate(value)),
-            _ => Err(format!("illegal mode: {}", mode))?,
+            Mode::Position => Ok(Parameter::Position(usize::try_from(value)?)),
+            Mode::Immediate => Ok(Parameter::Immediate(value)),
+            Mode::Relative => Err("relative mode is not supported")?,
         }
     }
     fn get_value(&self, input: &[i32]) -> i32 {
@@ -36,28 +36,20 @@ fn main() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
-fn get_opcode(input: &[i32], i: &mut usize) -> ([u8; 3], i32) {
-    let opcode: i32 = input[*i];
-    *i += 1;
-
-    let reversed_digits = opcode.digits().rev().collect::<Vec<u8>>();
-
-    let mode_1: u8 = reversed_digits.get(2).copied().unwrap_or(0);
-    let mode_2: u8 = reversed_digits.get(3).copied().unwrap_or(0);
-    let mode_3: u8 = reversed_digits.get(4).copied().unwrap_or(0);
-
-    let opcode = opcode % 100;
-
-    ([mode_1, mode_2, mode_3], opcode)
-}
-
-fn get_value(input: &[i32], i: &mut usize, mode: u8) -> Result<i32, Box<dyn Error>> {
-    let param = Parameter::new(mode, input[*i])?;
+fn get_value(input: &[i32], i: &mut usize, operand: Operand) -> Result<i32, Box<dyn Error>> {
+    let param = match operand {
+        Operand::Read(mode) => Parameter::new(mode, input[*i])?,
+        Operand::Write(_) => Err("expected read operand")?,
+    };
     *i += 1;
     Ok(param.get_value(input))
 }
-fn get_target(input: &[i32], i: &mut usize) -> Result<usize, Box<dyn Error>> {
-    let target = usize::try_from(input[*i])?;
+fn get_target(input: &[i32], i: &mut usize, operand: Operand) -> Result<usize, Box<dyn Error>> {
+    let target = match operand {
+        Operand::Write(Mode::Position) => usize::try_from(input[*i])?,
+        Operand::Write(mode) => Err(format!("unsupported write mode: {:?}", mode))?,
+        Operand::Read(_) => Err("expected write operand")?,
+    };
     *i += 1;
     Ok(target)
 }
@@ -65,48 +57,50 @@ fn get_target(input: &[i32], i: &mut usize) -> Result<usize, Box<dyn Error>> {
 fn run_program(mut input: Vec<i32>, system_id: i32) -> Result<Vec<i32>, Box<dyn Error>> {
     let mut i = 0;
     while i < input.len() {
-        let ([mode_1, mode_2, _mode_3], opcode) = get_opcode(&input, &mut i);
-        match opcode {
-            1 => {
-                let value_1 = get_value(&input, &mut i, mode_1)?;
-                let value_2 = get_value(&input, &mut i, mode_2)?;
-                let target = get_target(&input, &mut i)?;
+        let instruction = Instruction::decode(i64::from(input[i]))?;
+        let operands = &instruction.operands;
+        i += 1;
+        match instruction.opcode {
+            Opcode::Add => {
+                let value_1 = get_value(&input, &mut i, operands[0])?;
+                let value_2 = get_value(&input, &mut i, operands[1])?;
+                let target = get_target(&input, &mut i, operands[2])?;
                 input[target] = value_1 + value_2;
             }
-            2 => {
-                let value_1 = get_value(&input, &mut i, mode_1)?;
-                let value_2 = get_value(&input, &mut i, mode_2)?;
-                let target = get_target(&input, &mut i)?;
+            Opcode::Multiply => {
+                let value_1 = get_value(&input, &mut i, operands[0])?;
+                let value_2 = get_value(&input, &mut i, operands[1])?;
+                let target = get_target(&input, &mut i, operands[2])?;
                 input[target] = value_1 * value_2;
             }
-            3 => {
-                let target = get_target(&input, &mut i)?;
+            Opcode::Input => {
+                let target = get_target(&input, &mut i, operands[0])?;
                 input[target] = system_id;
             }
-            4 => {
-                let value_1 = get_value(&input, &mut i, mode_1)?;
+            Opcode::Output => {
+                let value_1 = get_value(&input, &mut i, operands[0])?;
                 println!("{}", value_1);
             }
-            5 => {
-                let value_1 = get_value(&input, &mut i, mode_1)?;
-                let value_2 = get_value(&input, &mut i, mode_2)?;
+            Opcode::JumpIfTrue => {
+                let value_1 = get_value(&input, &mut i, operands[0])?;
+                let value_2 = get_value(&input, &mut i, operands[1])?;
 
                 if value_1 != 0 {
                     i = usize::try_from(value_2)?;
                 }
             }
-            6 => {
-                let value_1 = get_value(&input, &mut i, mode_1)?;
-                let value_2 = get_value(&input, &mut i, mode_2)?;
+            Opcode::JumpIfFalse => {
+                let value_1 = get_value(&input, &mut i, operands[0])?;
+                let value_2 = get_value(&input, &mut i, operands[1])?;
 
                 if value_1 == 0 {
                     i = usize::try_from(value_2)?;
                 }
             }
-            7 => {
-                let value_1 = get_value(&input, &mut i, mode_1)?;
-                let value_2 = get_value(&input, &mut i, mode_2)?;
-                let target = get_target(&input, &mut i)?;
+            Opcode::LessThan => {
+                let value_1 = get_value(&input, &mut i, operands[0])?;
+                let value_2 = get_value(&input, &mut i, operands[1])?;
+                let target = get_target(&input, &mut i, operands[2])?;
 
                 if value_1 < value_2 {
                     input[target] = 1;
@@ -114,10 +108,10 @@ fn run_program(mut input: Vec<i32>, system_id: i32) -> Result<Vec<i32>, Box<dyn
                     input[target] = 0;
                 }
             }
-            8 => {
-                let value_1 = get_value(&input, &mut i, mode_1)?;
-                let value_2 = get_value(&input, &mut i, mode_2)?;
-                let target = get_target(&input, &mut i)?;
+            Opcode::Equals => {
+                let value_1 = get_value(&input, &mut i, operands[0])?;
+                let value_2 = get_value(&input, &mut i, operands[1])?;
+                let target = get_target(&input, &mut i, operands[2])?;
 
                 if value_1 == value_2 {
                     input[target] = 1;
@@ -126,11 +120,11 @@ fn run_program(mut input: Vec<i32>, system_id: i32) -> Result<Vec<i32>, Box<dyn
                 }
             }
 
-            99 => {
+            Opcode::Halt => {
                 println!("HALT");
                 break;
             }
-            x => panic!(format!("illegal opcode: {}", x)),
+            Opcode::AdjustRelativeBase => Err("relative base is not supported")?,
         }
     }
     Ok(input)
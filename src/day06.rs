@@ -1,77 +1,897 @@
-use std::collections::HashMap;
+use crate::export;
+use petgraph::visit::EdgeRef;
+use petgraph::Graph;
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::fmt::{self, Write};
+use std::path::PathBuf;
 
+const CENTER: &str = "COM";
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum OrbitError {
+    Malformed {
+        line: usize,
+        text: String,
+    },
+    MultipleParents {
+        line: usize,
+        object: String,
+        first_line: usize,
+    },
+    Cycle {
+        line: usize,
+        objects: Vec<String>,
+    },
+    Orphan {
+        line: usize,
+        root: String,
+    },
+}
+
+impl fmt::Display for OrbitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OrbitError::Malformed { line, text } => {
+                write!(f, "line {}: expected A)B, found {:?}", line, text)
+            }
+            OrbitError::MultipleParents {
+                line,
+                object,
+                first_line,
+            } => write!(
+                f,
+                "line {}: {} already orbits something else (line {})",
+                line, object, first_line
+            ),
+            OrbitError::Cycle { line, objects } => write!(
+                f,
+                "line {}: orbits form a cycle: {} -> {}",
+                line,
+                objects.join(" -> "),
+                objects[0]
+            ),
+            OrbitError::Orphan { line, root } => write!(
+                f,
+                "line {}: {} does not orbit anything and is not {}",
+                line, root, CENTER
+            ),
+        }
+    }
+}
+
+impl Error for OrbitError {}
+
+pub struct OrbitMap {
+    names: Vec<String>,
+    ids: HashMap<String, usize>,
+    first_seen: Vec<usize>,
+    parents: Vec<Option<(usize, usize)>>,
+    children: Vec<Vec<usize>>,
+    depths: Vec<usize>,
+    subtree_sizes: Vec<usize>,
+}
+
+impl OrbitMap {
+    pub fn parse(input: &str) -> Result<OrbitMap, OrbitError> {
+        let mut map = OrbitMap {
+            names: Vec::new(),
+            ids: HashMap::new(),
+            first_seen: Vec::new(),
+            parents: Vec::new(),
+            children: Vec::new(),
+            depths: Vec::new(),
+            subtree_sizes: Vec::new(),
+        };
+        map.id(CENTER, 0);
+
+        for (i, text) in input.lines().enumerate() {
+            let line = i + 1;
+            let text = text.trim();
+            if text.is_empty() {
+                continue;
+            }
+
+            let malformed = || OrbitError::Malformed {
+                line,
+                text: text.to_string(),
+            };
+            let mut objects = text.split(')');
+            let (parent, child) = match (objects.next(), objects.next(), objects.next()) {
+                (Some(parent), Some(child), None) if !parent.is_empty() && !child.is_empty() => {
+                    (parent, child)
+                }
+                _ => return Err(malformed()),
+            };
+
+            let parent = map.id(parent, line);
+            let child = map.id(child, line);
+            if let Some((_, first_line)) = map.parents[child] {
+                return Err(OrbitError::MultipleParents {
+                    line,
+                    object: map.names[child].clone(),
+                    first_line,
+                });
+            }
+            map.parents[child] = Some((parent, line));
+            map.children[parent].push(child);
+        }
+
+        map.compute_depths()?;
+        Ok(map)
+    }
+
+    fn id(&mut self, name: &str, line: usize) -> usize {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+        let id = self.names.len();
+        self.names.push(name.to_string());
+        self.ids.insert(name.to_string(), id);
+        self.first_seen.push(line);
+        self.parents.push(None);
+        self.children.push(Vec::new());
+        id
+    }
+
+    fn parent(&self, id: usize) -> Option<usize> {
+        self.parents[id].map(|(parent, _)| parent)
+    }
+
+    // Orbital transfers move between the objects that a and b orbit.
+    fn orbited(&self, name: &str) -> Result<usize, Box<dyn Error>> {
+        let parent = self.parent(self.lookup(name)?);
+        Ok(parent.ok_or_else(|| format!("{} does not orbit anything", name))?)
+    }
+
+    // Depths come from a walk down from COM rather than from line order, so
+    // anything left unvisited is either in a cycle or hangs off another root.
+    // Both passes are loops over a queue, so long chains cannot overflow the stack.
+    fn compute_depths(&mut self) -> Result<(), OrbitError> {
+        let mut depths = vec![None; self.names.len()];
+        let mut order = Vec::with_capacity(self.names.len());
+        let mut queue = VecDeque::new();
+        depths[0] = Some(0);
+        queue.push_back(0);
+        while let Some(id) = queue.pop_front() {
+            order.push(id);
+            let depth = depths[id].unwrap_or(0) + 1;
+            for &child in &self.children[id] {
+                // only COM can be reached twice, through a cycle back to it
+                if depths[child].is_some() {
+                    return Err(self.unreached_error(child));
+                }
+                depths[child] = Some(depth);
+                queue.push_back(child);
+            }
+        }
+
+        if let Some(unreached) = depths.iter().position(Option::is_none) {
+            return Err(self.unreached_error(unreached));
+        }
+        self.depths = depths.into_iter().map(|depth| depth.unwrap_or(0)).collect();
+
+        // children come after their parents in breadth-first order, so going
+        // backwards every subtree is complete before it is added to its parent
+        self.subtree_sizes = vec![1; self.names.len()];
+        for &id in order.iter().rev() {
+            if let Some(parent) = self.parent(id) {
+                self.subtree_sizes[parent] += self.subtree_sizes[id];
+            }
+        }
+        Ok(())
+    }
+
+    fn unreached_error(&self, start: usize) -> OrbitError {
+        let mut path = vec![start];
+        let mut seen = vec![false; self.names.len()];
+        seen[start] = true;
+
+        let mut id = start;
+        while let Some(parent) = self.parent(id) {
+            if seen[parent] {
+                let cycle_start = path.iter().position(|&id| id == parent).unwrap_or(0);
+                let mut cycle = path.split_off(cycle_start);
+                // list the cycle in orbit order, starting where the walk entered it
+                cycle.reverse();
+                cycle.rotate_right(1);
+                let line = cycle
+                    .iter()
+                    .filter_map(|&id| self.parents[id].map(|(_, line)| line))
+                    .max()
+                    .unwrap_or(0);
+                return OrbitError::Cycle {
+                    line,
+                    objects: cycle.iter().map(|&id| self.names[id].clone()).collect(),
+                };
+            }
+            seen[parent] = true;
+            path.push(parent);
+            id = parent;
+        }
+
+        OrbitError::Orphan {
+            line: self.first_seen[id],
+            root: self.names[id].clone(),
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Result<usize, Box<dyn Error>> {
+        self.ids
+            .get(name)
+            .copied()
+            .ok_or_else(|| format!("unknown object {}", name).into())
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn depth(&self, name: &str) -> Option<usize> {
+        self.ids.get(name).map(|&id| self.depths[id])
+    }
+
+    pub fn subtree_size(&self, name: &str) -> Option<usize> {
+        self.ids.get(name).map(|&id| self.subtree_sizes[id])
+    }
+
+    pub fn depths(&self) -> impl Iterator<Item = (&str, usize)> + '_ {
+        self.names
+            .iter()
+            .zip(&self.depths)
+            .map(|(name, &depth)| (name.as_str(), depth))
+    }
+
+    pub fn total_orbits(&self) -> usize {
+        self.depths.iter().sum()
+    }
+
+    pub fn lca_index(&self) -> LcaIndex<'_> {
+        LcaIndex::new(self)
+    }
+
+    // Climbs both ancestor chains, which needs no index for a one-off query.
+    pub fn transfer_distance(&self, a: &str, b: &str) -> Result<usize, Box<dyn Error>> {
+        Ok(self.path(self.orbited(a)?, self.orbited(b)?).len() - 1)
+    }
+
+    pub fn ancestors(&self, name: &str) -> Result<Vec<&str>, Box<dyn Error>> {
+        let mut result = Vec::new();
+        let mut id = self.lookup(name)?;
+        while let Some(parent) = self.parent(id) {
+            result.push(self.names[parent].as_str());
+            id = parent;
+        }
+        Ok(result)
+    }
+
+    // Every object from a up to where the two ancestor chains meet and back
+    // down to b, in that order.
+    fn path(&self, mut a: usize, mut b: usize) -> Vec<usize> {
+        let (mut up, mut down) = (Vec::new(), Vec::new());
+        while self.depths[a] > self.depths[b] {
+            up.push(a);
+            a = self.parent(a).unwrap_or(0);
+        }
+        while self.depths[b] > self.depths[a] {
+            down.push(b);
+            b = self.parent(b).unwrap_or(0);
+        }
+        while a != b {
+            up.push(a);
+            down.push(b);
+            a = self.parent(a).unwrap_or(0);
+            b = self.parent(b).unwrap_or(0);
+        }
+        up.push(a);
+        up.extend(down.into_iter().rev());
+        up
+    }
+
+    // Edges point from the orbited object to the one orbiting it. When chains
+    // are collapsed, only COM, objects that don't have exactly one satellite
+    // and the ends and turning point of the highlighted path remain.
+    pub fn to_graph(&self, options: ExportOptions) -> Result<OrbitGraph<'_>, Box<dyn Error>> {
+        let mut highlighted = vec![false; self.len()];
+        let mut kept = vec![!options.collapse_chains; self.len()];
+        kept[0] = true;
+        for (id, children) in self.children.iter().enumerate() {
+            if children.len() != 1 {
+                kept[id] = true;
+            }
+        }
+        if let Some((from, to)) = options.highlight {
+            let path = self.path(self.lookup(from)?, self.lookup(to)?);
+            let turn = path.iter().copied().min_by_key(|&id| self.depths[id]);
+            for &id in &path {
+                highlighted[id] = true;
+            }
+            for id in path.first().into_iter().chain(path.last()).chain(&turn) {
+                kept[*id] = true;
+            }
+        }
+
+        let mut graph = Graph::new();
+        let nodes = (0..self.len())
+            .map(|id| {
+                if kept[id] {
+                    Some(graph.add_node(OrbitNode {
+                        name: &self.names[id],
+                        highlighted: highlighted[id],
+                    }))
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+
+        for (id, node) in nodes.iter().enumerate() {
+            let (node, mut parent) = match (node, self.parent(id)) {
+                (Some(node), Some(parent)) => (*node, parent),
+                _ => continue,
+            };
+            let mut length = 1;
+            while !kept[parent] {
+                parent = self.parent(parent).unwrap_or(0);
+                length += 1;
+            }
+            if let Some(parent_node) = nodes[parent] {
+                let edge = OrbitEdge {
+                    length,
+                    highlighted: highlighted[id] && highlighted[parent],
+                };
+                graph.add_edge(parent_node, node, edge);
+            }
+        }
+        Ok(graph)
+    }
+
+    pub fn to_dot(&self, options: ExportOptions) -> Result<String, Box<dyn Error>> {
+        let mut dot = String::new();
+        write_dot(&mut dot, &self.to_graph(options)?)?;
+        Ok(dot)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct ExportOptions<'a> {
+    highlight: Option<(&'a str, &'a str)>,
+    collapse_chains: bool,
+}
+
+impl<'a> ExportOptions<'a> {
+    pub fn highlight(mut self, from: &'a str, to: &'a str) -> ExportOptions<'a> {
+        self.highlight = Some((from, to));
+        self
+    }
+
+    pub fn collapse_chains(mut self) -> ExportOptions<'a> {
+        self.collapse_chains = true;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct OrbitNode<'a> {
+    pub name: &'a str,
+    pub highlighted: bool,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct OrbitEdge {
+    pub length: usize,
+    pub highlighted: bool,
+}
+
+pub type OrbitGraph<'a> = Graph<OrbitNode<'a>, OrbitEdge>;
+
+const HIGHLIGHT: &str = "red";
+
+pub fn write_dot(out: &mut impl Write, graph: &OrbitGraph) -> fmt::Result {
+    // node ids are graph indices, so names only ever appear as quoted labels
+    let quoted = |name: &str| format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""));
+
+    writeln!(out, "digraph orbits {{")?;
+    writeln!(out, "    rankdir=LR;")?;
+    for index in graph.node_indices() {
+        let node = graph[index];
+        write!(out, "    n{} [label={}", index.index(), quoted(node.name))?;
+        if node.highlighted {
+            write!(out, ", color={0}, fontcolor={0}", HIGHLIGHT)?;
+        }
+        writeln!(out, "];")?;
+    }
+    for edge in graph.edge_references() {
+        let mut attributes = Vec::new();
+        if edge.weight().length > 1 {
+            attributes.push(format!("label=\"{}\"", edge.weight().length));
+        }
+        if edge.weight().highlighted {
+            attributes.push(format!("color={}, penwidth=2", HIGHLIGHT));
+        }
+        write!(
+            out,
+            "    n{} -> n{}",
+            edge.source().index(),
+            edge.target().index()
+        )?;
+        if !attributes.is_empty() {
+            write!(out, " [{}]", attributes.join(", "))?;
+        }
+        writeln!(out, ";")?;
+    }
+    writeln!(out, "}}")
+}
+
+// Lowest common ancestors via an Euler tour of the tree and a sparse table of
+// range minimums over it, so each query is two table lookups.
+pub struct LcaIndex<'a> {
+    map: &'a OrbitMap,
+    first_visit: Vec<usize>,
+    tour: Vec<usize>,
+    table: Vec<Vec<usize>>,
+}
+
+impl<'a> LcaIndex<'a> {
+    fn new(map: &'a OrbitMap) -> LcaIndex<'a> {
+        let mut first_visit = vec![0; map.len()];
+        let mut tour = Vec::with_capacity(2 * map.len());
+        let mut stack = vec![(0, 0)];
+        while let Some(&mut (id, ref mut next_child)) = stack.last_mut() {
+            if *next_child == 0 {
+                first_visit[id] = tour.len();
+            }
+            tour.push(id);
+            match map.children[id].get(*next_child) {
+                Some(&child) => {
+                    *next_child += 1;
+                    stack.push((child, 0));
+                }
+                None => {
+                    stack.pop();
+                }
+            }
+        }
+
+        let shallower = |a: usize, b: usize| {
+            if map.depths[a] <= map.depths[b] {
+                a
+            } else {
+                b
+            }
+        };
+        let mut table = vec![tour.clone()];
+        let mut width = 1;
+        while 2 * width <= tour.len() {
+            let previous = &table[table.len() - 1];
+            let level = (0..=tour.len() - 2 * width)
+                .map(|i| shallower(previous[i], previous[i + width]))
+                .collect();
+            table.push(level);
+            width *= 2;
+        }
+
+        LcaIndex {
+            map,
+            first_visit,
+            tour,
+            table,
+        }
+    }
+
+    fn lca_id(&self, a: usize, b: usize) -> usize {
+        let (a, b) = (self.first_visit[a], self.first_visit[b]);
+        let (start, end) = (a.min(b), a.max(b) + 1);
+        let level = (usize::BITS - 1 - (end - start).leading_zeros()) as usize;
+        let (x, y) = (
+            self.table[level][start],
+            self.table[level][end - (1 << level)],
+        );
+        if self.map.depths[x] <= self.map.depths[y] {
+            x
+        } else {
+            y
+        }
+    }
+
+    pub fn lca(&self, a: &str, b: &str) -> Result<&'a str, Box<dyn Error>> {
+        let id = self.lca_id(self.map.lookup(a)?, self.map.lookup(b)?);
+        Ok(&self.map.names[id])
+    }
+
+    pub fn distance(&self, a: &str, b: &str) -> Result<usize, Box<dyn Error>> {
+        let (a, b) = (self.map.lookup(a)?, self.map.lookup(b)?);
+        let depths = &self.map.depths;
+        Ok(depths[a] + depths[b] - 2 * depths[self.lca_id(a, b)])
+    }
+
+    pub fn transfer_distance(&self, a: &str, b: &str) -> Result<usize, Box<dyn Error>> {
+        let (a, b) = (self.map.orbited(a)?, self.map.orbited(b)?);
+        let depths = &self.map.depths;
+        Ok(depths[a] + depths[b] - 2 * depths[self.lca_id(a, b)])
+    }
+
+    // One "A B" (or "A,B") pair per line.
+    pub fn transfer_queries(&self, input: &str) -> Result<Vec<usize>, Box<dyn Error>> {
+        let mut distances = Vec::new();
+        for (i, line) in input.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let names = line
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|name| !name.is_empty())
+                .collect::<Vec<_>>();
+            let distance = match names.as_slice() {
+                [a, b] => self.transfer_distance(a, b),
+                _ => Err(format!("expected two objects, found {:?}", line).into()),
+            };
+            distances.push(distance.map_err(|e| format!("line {}: {}", i + 1, e))?);
+        }
+        Ok(distances)
+    }
+
+    pub fn tour_len(&self) -> usize {
+        self.tour.len()
+    }
+}
+
+// An optional first argument names a file of batch transfer queries.
 pub fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let queries = std::env::args_os().nth(1).map(PathBuf::from);
+
     let input = std::fs::read_to_string("day06/input.txt")?;
-    let mut orbit_counts: HashMap<&str, u32> = HashMap::new();
-    let mut children: HashMap<&str, Vec<&str>> = HashMap::new();
-    let mut parents: HashMap<&str, &str> = HashMap::new();
-    input
-        .lines()
-        .map(|line| {
-            let mut obj_iter = line.split(')');
-            let parent: &str = obj_iter.next().unwrap();
-            let child: &str = obj_iter.next().unwrap();
-
-            (parent, child)
-        })
-        .for_each(|(parent, child)| {
-            let children = children.entry(parent).or_insert(Vec::new());
-            children.push(child);
-
-            let parent_orbits = orbit_counts.get(parent).copied().unwrap_or(0);
-            orbit_counts.insert(child, parent_orbits + 1);
-
-            parents.insert(child, parent);
-        });
-
-    let total_orbits: usize = sum_depths(0, "COM", &children);
+    let map = OrbitMap::parse(&input)?;
+
+    let total_orbits = map.total_orbits();
 
     println!("{}", total_orbits);
 
-    let my_parents = get_ancestors("YOU", &parents);
-    let santas_parents = get_ancestors("SAN", &parents);
-
-    let common_ancestor: &str = my_parents
-        .iter()
-        .rev()
-        .zip(santas_parents.iter().rev())
-        .filter(|(a, b)| a == b)
-        .last()
-        .map(|(a, _)| a)
-        .unwrap();
-
-    let ancestor_to_me = my_parents
-        .iter()
-        .position(|&x| x == common_ancestor)
-        .unwrap();
-    let ancestor_to_santa = santas_parents
-        .iter()
-        .position(|&x| x == common_ancestor)
-        .unwrap();
-    let distance_to_santa = ancestor_to_me + ancestor_to_santa;
+    let distance_to_santa = map.transfer_distance("YOU", "SAN")?;
 
     println!("{}", distance_to_santa);
 
+    if let Some(path) = export::export_path("day06_orbits.dot")? {
+        let options = ExportOptions::default()
+            .highlight("YOU", "SAN")
+            .collapse_chains();
+        std::fs::write(path, map.to_dot(options)?)?;
+    }
+
+    if let Some(path) = queries {
+        let queries = std::fs::read_to_string(path)?;
+        let index = map.lca_index();
+        for distance in index.transfer_queries(&queries)? {
+            println!("{}", distance);
+        }
+    }
+
     Ok(())
 }
 
-fn get_ancestors<'a>(mut child: &'a str, parents: &'a HashMap<&str, &str>) -> Vec<&'a str> {
-    let mut result = Vec::new();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rng::XorShift;
+
+    const EXAMPLE: &str = "COM)B\nB)C\nC)D\nD)E\nE)F\nB)G\nG)H\nD)I\nE)J\nJ)K\nK)L\n";
 
-    while let Some(&parent) = parents.get(child) {
-        result.push(parent);
-        child = parent;
+    #[test]
+    fn test_total_orbits() {
+        let map = OrbitMap::parse(EXAMPLE).unwrap();
+        assert_eq!(map.len(), 12);
+        assert_eq!(map.total_orbits(), 42);
+        assert_eq!(map.depth("L"), Some(7));
+        assert_eq!(map.ancestors("D").unwrap(), vec!["C", "B", "COM"]);
     }
 
-    result
-}
+    #[test]
+    fn test_line_order_does_not_matter() {
+        let reversed = EXAMPLE.lines().rev().collect::<Vec<_>>().join("\n");
+        let map = OrbitMap::parse(&reversed).unwrap();
+        assert_eq!(map.total_orbits(), 42);
+        assert_eq!(map.depth("L"), Some(7));
+        assert_eq!(map.depth("COM"), Some(0));
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        let error = |input: &str| OrbitMap::parse(input).err().unwrap();
+
+        assert_eq!(
+            error("COM)B\nB-C\n"),
+            OrbitError::Malformed {
+                line: 2,
+                text: "B-C".to_string()
+            }
+        );
+        assert_eq!(
+            error("COM)B\nB)C)D\n").to_string(),
+            "line 2: expected A)B, found \"B)C)D\""
+        );
+        assert_eq!(
+            error("COM)B\nCOM)C\nB)D\nC)D\n"),
+            OrbitError::MultipleParents {
+                line: 4,
+                object: "D".to_string(),
+                first_line: 3
+            }
+        );
+        assert_eq!(
+            error("COM)B\nX)Y\nY)Z\nZ)X\n"),
+            OrbitError::Cycle {
+                line: 4,
+                objects: vec!["X".to_string(), "Y".to_string(), "Z".to_string()]
+            }
+        );
+        assert_eq!(
+            error("COM)B\nB)C\nQ)R\nR)S\n").to_string(),
+            "line 3: Q does not orbit anything and is not COM"
+        );
+        assert!(matches!(error("A)A\n"), OrbitError::Cycle { line: 1, .. }));
+        assert_eq!(
+            error("COM)B\nB)COM\n"),
+            OrbitError::Cycle {
+                line: 2,
+                objects: vec!["COM".to_string(), "B".to_string()]
+            }
+        );
+        assert!(matches!(
+            error("COM)B\nB)C\nC)COM\n"),
+            OrbitError::Cycle { line: 3, .. }
+        ));
+    }
+
+    #[test]
+    fn test_transfer_distance() {
+        let input = format!("{}K)YOU\nI)SAN\n", EXAMPLE);
+        let map = OrbitMap::parse(&input).unwrap();
+        let index = map.lca_index();
+
+        assert_eq!(index.lca("YOU", "SAN").unwrap(), "D");
+        assert_eq!(index.lca("L", "L").unwrap(), "L");
+        assert_eq!(index.lca("H", "COM").unwrap(), "COM");
+        assert_eq!(index.distance("YOU", "SAN").unwrap(), 6);
+        assert_eq!(index.transfer_distance("YOU", "SAN").unwrap(), 4);
+        assert!(index.transfer_distance("COM", "SAN").is_err());
+        assert_eq!(map.transfer_distance("YOU", "SAN").unwrap(), 4);
+        assert_eq!(map.transfer_distance("YOU", "YOU").unwrap(), 0);
+        assert!(map.transfer_distance("COM", "SAN").is_err());
+        assert!(index.lca("YOU", "NOBODY").is_err());
+    }
+
+    #[test]
+    fn test_lca_matches_ancestor_walk() {
+        // object i orbits a pseudo-random earlier object
+        let mut rng = XorShift(0x2019_0006);
+        let mut input = String::new();
+        for i in 1..500u64 {
+            let parent = rng.below(i);
+            let name = |id: u64| {
+                if id == 0 {
+                    CENTER.to_string()
+                } else {
+                    format!("N{}", id)
+                }
+            };
+            input.push_str(&format!("{}){}\n", name(parent), name(i)));
+        }
+        let map = OrbitMap::parse(&input).unwrap();
+        let index = map.lca_index();
+        assert_eq!(index.tour_len(), 2 * map.len() - 1);
+
+        let naive = |a: &str, b: &str| {
+            let mut a_line = map.ancestors(a).unwrap();
+            a_line.insert(0, map.names[map.ids[a]].as_str());
+            let mut b_line = map.ancestors(b).unwrap();
+            b_line.insert(0, map.names[map.ids[b]].as_str());
+            *a_line.iter().find(|name| b_line.contains(name)).unwrap()
+        };
+        for a in (1..500).step_by(7) {
+            for b in (1..500).step_by(11) {
+                let (a, b) = (format!("N{}", a), format!("N{}", b));
+                assert_eq!(index.lca(&a, &b).unwrap(), naive(&a, &b), "{} {}", a, b);
+                assert_eq!(
+                    index.transfer_distance(&a, &b).unwrap(),
+                    map.transfer_distance(&a, &b).unwrap()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_transfer_queries() {
+        let input = format!("{}K)YOU\nI)SAN\n", EXAMPLE);
+        let map = OrbitMap::parse(&input).unwrap();
+        let index = map.lca_index();
+
+        let queries = "YOU SAN\n\nL,H\n  F   G  \n";
+        assert_eq!(index.transfer_queries(queries).unwrap(), vec![4, 6, 3]);
+
+        let error = index.transfer_queries("YOU SAN\nYOU\n").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "line 2: expected two objects, found \"YOU\""
+        );
+        let error = index.transfer_queries("YOU MARS\n").unwrap_err();
+        assert_eq!(error.to_string(), "line 1: unknown object MARS");
+    }
+
+    #[test]
+    fn test_subtree_sizes() {
+        let map = OrbitMap::parse(EXAMPLE).unwrap();
+        assert_eq!(map.subtree_size("COM"), Some(12));
+        assert_eq!(map.subtree_size("E"), Some(5));
+        assert_eq!(map.subtree_size("H"), Some(1));
+        assert_eq!(map.subtree_size("X"), None);
+
+        // each object is orbited directly or indirectly by its whole subtree
+        let strict_descendants = map.subtree_sizes.iter().map(|size| size - 1).sum::<usize>();
+        assert_eq!(strict_descendants, map.total_orbits());
+        assert_eq!(map.depths().map(|(_, depth)| depth).max(), Some(7));
+    }
+
+    #[test]
+    fn test_long_chain() {
+        let n = 1_000_000;
+        let mut input = String::with_capacity(n * 16);
+        input.push_str("COM)O1\n");
+        for i in 1..n {
+            input.push_str(&format!("O{})O{}\n", i, i + 1));
+        }
+
+        let map = OrbitMap::parse(&input).unwrap();
+        let last = format!("O{}", n);
+        assert_eq!(map.len(), n + 1);
+        assert_eq!(map.total_orbits(), n * (n + 1) / 2);
+        assert_eq!(map.depth(&last), Some(n));
+        assert_eq!(map.subtree_size("O1"), Some(n));
+        assert_eq!(map.ancestors(&last).unwrap().len(), n);
+    }
+
+    #[test]
+    fn test_long_chain_lca() {
+        let n = 200_000;
+        let mut input = String::from("COM)O1\nCOM)YOU\n");
+        for i in 1..n {
+            input.push_str(&format!("O{})O{}\n", i, i + 1));
+        }
+        input.push_str(&format!("O{})SAN\n", n));
 
-fn sum_depths(depth: usize, root: &str, children: &HashMap<&str, Vec<&str>>) -> usize {
-    let empty = Vec::new();
-    let direct_children = children.get(root).unwrap_or(&empty);
-    direct_children.len() * (depth + 1)
-        + direct_children
+        let map = OrbitMap::parse(&input).unwrap();
+        let index = map.lca_index();
+        assert_eq!(index.lca("SAN", "O1").unwrap(), "O1");
+        assert_eq!(index.transfer_distance("YOU", "SAN").unwrap(), n);
+
+        let cycle = input.replacen("COM)O1", &format!("O{})O1", n), 1);
+        assert!(matches!(
+            OrbitMap::parse(&cycle),
+            Err(OrbitError::Cycle { .. })
+        ));
+    }
+
+    #[test]
+    fn test_to_graph() {
+        use petgraph::algo::dijkstra;
+
+        let input = format!("{}K)YOU\nI)SAN\n", EXAMPLE);
+        let map = OrbitMap::parse(&input).unwrap();
+        let graph = map
+            .to_graph(ExportOptions::default().highlight("YOU", "SAN"))
+            .unwrap();
+        assert_eq!(graph.node_count(), map.len());
+        assert_eq!(graph.edge_count(), map.len() - 1);
+
+        let path = graph
+            .raw_nodes()
             .iter()
-            .map(|child| sum_depths(depth + 1, child, children))
-            .sum::<usize>()
+            .map(|node| node.weight)
+            .filter(|node| node.highlighted)
+            .map(|node| node.name)
+            .collect::<Vec<_>>();
+        assert_eq!(path.len(), 7);
+        let highlighted_edges = graph
+            .raw_edges()
+            .iter()
+            .map(|edge| edge.weight)
+            .filter(|edge| edge.highlighted);
+        assert_eq!(highlighted_edges.count(), 6);
+
+        let com = graph
+            .node_indices()
+            .find(|&i| graph[i].name == CENTER)
+            .unwrap();
+        let depths = dijkstra(&graph, com, None, |edge| edge.weight().length);
+        for (index, depth) in depths {
+            assert_eq!(map.depth(graph[index].name), Some(depth));
+        }
+    }
+
+    #[test]
+    fn test_collapse_chains() {
+        use petgraph::algo::dijkstra;
+
+        let map = OrbitMap::parse(EXAMPLE).unwrap();
+        let graph = map
+            .to_graph(ExportOptions::default().collapse_chains())
+            .unwrap();
+        let mut edges = graph
+            .edge_references()
+            .map(|edge| {
+                let (source, target) = (graph[edge.source()].name, graph[edge.target()].name);
+                (source, target, edge.weight().length)
+            })
+            .collect::<Vec<_>>();
+        edges.sort();
+        assert_eq!(
+            edges,
+            vec![
+                ("B", "D", 2),
+                ("B", "H", 2),
+                ("COM", "B", 1),
+                ("D", "E", 1),
+                ("D", "I", 1),
+                ("E", "F", 1),
+                ("E", "L", 3),
+            ]
+        );
+
+        // the highlighted path keeps its ends and turning point as nodes
+        let input = format!("{}K)YOU\nI)SAN\n", EXAMPLE);
+        let map = OrbitMap::parse(&input).unwrap();
+        let options = ExportOptions::default()
+            .highlight("K", "C")
+            .collapse_chains();
+        let graph = map.to_graph(options).unwrap();
+        let names = graph
+            .raw_nodes()
+            .iter()
+            .map(|node| node.weight)
+            .map(|node| node.name)
+            .collect::<Vec<_>>();
+        assert!(names.contains(&"K") && names.contains(&"C"));
+        let highlighted = graph
+            .raw_edges()
+            .iter()
+            .map(|edge| edge.weight)
+            .filter(|edge| edge.highlighted)
+            .map(|edge| edge.length)
+            .sum::<usize>();
+        assert_eq!(highlighted, 4);
+
+        let com = graph
+            .node_indices()
+            .find(|&i| graph[i].name == CENTER)
+            .unwrap();
+        let depths = dijkstra(&graph, com, None, |edge| edge.weight().length);
+        assert_eq!(depths.len(), graph.node_count());
+        for (index, depth) in depths {
+            assert_eq!(map.depth(graph[index].name), Some(depth));
+        }
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let map = OrbitMap::parse("COM)A\nA)B\nB)C\nB)\"D\"\n").unwrap();
+        let options = ExportOptions::default()
+            .highlight("C", "COM")
+            .collapse_chains();
+        let expected = concat!(
+            "digraph orbits {\n",
+            "    rankdir=LR;\n",
+            "    n0 [label=\"COM\", color=red, fontcolor=red];\n",
+            "    n1 [label=\"B\", color=red, fontcolor=red];\n",
+            "    n2 [label=\"C\", color=red, fontcolor=red];\n",
+            "    n3 [label=\"\\\"D\\\"\"];\n",
+            "    n0 -> n1 [label=\"2\", color=red, penwidth=2];\n",
+            "    n1 -> n2 [color=red, penwidth=2];\n",
+            "    n1 -> n3;\n",
+            "}\n",
+        );
+        assert_eq!(map.to_dot(options).unwrap(), expected);
+        assert!(map
+            .to_dot(ExportOptions::default().highlight("C", "X"))
+            .is_err());
+    }
 }
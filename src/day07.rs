@@ -1,18 +1,22 @@
-use digits_iterator::*;
+use crate::intcode::{Instruction, Mode, Opcode, Operand};
 use itertools::Itertools;
+use rayon::prelude::*;
+use std::cmp::Reverse;
+use std::collections::VecDeque;
 use std::convert::TryFrom;
 use std::error::Error;
+use std::ops::RangeInclusive;
 
 enum Parameter {
     Position(usize),
     Immediate(i32),
 }
 impl Parameter {
-    fn new(mode: u8, value: i32) -> Result<Parameter, Box<dyn Error>> {
+    fn new(mode: Mode, value: i32) -> Result<Parameter, Box<dyn Error>> {
         match mode {
-            0 => Ok(Parameter::Position(usize::try_from(value)?)),
-            1 => Ok(Parameter::Immediate(value)),
-            _ => Err(format!("illegal mode: {}", mode))?,
+            Mode::Position => Ok(Parameter::Position(usize::try_from(value)?)),
+            Mode::Immediate => Ok(Parameter::Immediate(value)),
+            Mode::Relative => Err("relative mode is not supported")?,
         }
     }
     fn get_value(&self, input: &[i32]) -> i32 {
@@ -47,66 +51,181 @@ pub fn main() -> Result<(), Box<dyn Error>> {
         3, 9, 1002, 9, 2, 9, 4, 9, 99,
     ];
 
-    let amplifiers = 0..=4;
-    let part1_ans = amplifiers
-        .permutations(5)
-        .filter_map(|sequence| run_amplifier_sequence(&instructions, &sequence).ok())
-        .max()
-        .unwrap();
+    let part1 = search_phases(&instructions, 0..=4, 5, run_amplifier_sequence);
+    let part2 = search_phases(&instructions, 5..=9, 5, run_looped_amplifier_sequence);
 
-    println!("{}", part1_ans);
+    for search in &[part1, part2] {
+        for (sequence, error) in &search.errors {
+            eprintln!("{:?} failed: {}", sequence, error);
+        }
+        let (sequence, signal) = search.best()?;
+        println!("{:?}", sequence);
+        println!("{}", signal);
+    }
 
-    let amplifiers = 5..=9;
+    Ok(())
+}
 
-    let part2_ans = amplifiers
-    .permutations(5)
-    .filter_map(|sequence| run_looped_amplifier_sequence(&instructions, &sequence).ok())
-    .max()
-    .unwrap();
+struct PhaseSearch {
+    best: Option<(Vec<i32>, i32)>,
+    errors: Vec<(Vec<i32>, String)>,
+}
 
-    println!("{}", part2_ans);
+impl PhaseSearch {
+    fn best(&self) -> Result<(&[i32], i32), Box<dyn Error>> {
+        let (sequence, signal) = self.best.as_ref().ok_or("no valid phase sequence")?;
+        Ok((sequence, *signal))
+    }
+}
 
-    Ok(())
+fn search_phases<F>(
+    instructions: &[i32],
+    phases: RangeInclusive<i32>,
+    amplifiers: usize,
+    run_sequence: F,
+) -> PhaseSearch
+where
+    F: Fn(&[i32], &[i32]) -> Result<i32, Box<dyn Error>> + Sync,
+{
+    let (results, errors): (Vec<_>, Vec<_>) = phases
+        .permutations(amplifiers)
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .map(|sequence| {
+            let result = run_sequence(instructions, &sequence).map_err(|e| e.to_string());
+            (sequence, result)
+        })
+        .partition(|(_, result)| result.is_ok());
+
+    let best = results
+        .into_iter()
+        .filter_map(|(sequence, result)| result.ok().map(|signal| (sequence, signal)))
+        .max_by_key(|(sequence, signal)| (*signal, Reverse(sequence.clone())));
+
+    let mut errors = errors
+        .into_iter()
+        .filter_map(|(sequence, result)| result.err().map(|error| (sequence, error)))
+        .collect::<Vec<_>>();
+    errors.sort();
+
+    PhaseSearch { best, errors }
 }
 
-fn run_looped_amplifier_sequence(instructions: &Vec<i32>, phase_settings: &[i32]) -> Result<i32, Box<dyn Error>> {
+fn run_looped_amplifier_sequence(
+    instructions: &[i32],
+    phase_settings: &[i32],
+) -> Result<i32, Box<dyn Error>> {
+    Topology::ring(phase_settings).run(instructions)
+}
 
-    let mut programs = Vec::new();
-    for &phase_setting in phase_settings {
-        let mut program = Program::new(instructions.clone());
-        program.run(Some(phase_setting))?;
-        programs.push(program)
-    }
+fn run_amplifier_sequence(instructions: &[i32], sequence: &[i32]) -> Result<i32, Box<dyn Error>> {
+    Topology::chain(sequence).run(instructions)
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Stop {
+    AllHalted,
+    OutputHalted,
+}
 
-    let mut previous_output = 0;
-    for amp_id in (0..phase_settings.len()).cycle() {
-        let program = programs.get_mut(amp_id).unwrap();
-        let output = program.run(Some(previous_output))?;
+#[derive(Debug, Clone)]
+struct Topology {
+    phases: Vec<i32>,
+    edges: Vec<(usize, usize)>,
+    inputs: Vec<(usize, i32)>,
+    output: usize,
+    stop: Stop,
+}
 
-        match output {
-            Output::Value(value) => previous_output = value,
-            Output::Halted => return Ok(previous_output),
-            _ => ()
+impl Topology {
+    fn chain(phases: &[i32]) -> Self {
+        let edges = (1..phases.len()).map(|to| (to - 1, to)).collect();
+        Topology {
+            phases: phases.to_vec(),
+            edges,
+            inputs: vec![(0, 0)],
+            output: phases.len().saturating_sub(1),
+            stop: Stop::AllHalted,
         }
     }
 
-    Err("no result")?
-}
+    fn ring(phases: &[i32]) -> Self {
+        let mut topology = Topology::chain(phases);
+        topology.edges.push((topology.output, 0));
+        topology
+    }
 
-fn run_amplifier_sequence(instructions: &Vec<i32>, sequence: &[i32]) -> Result<i32, Box<dyn Error>> {
-    let mut previous_output = 0;
-    for &x in sequence {
-        let instructions = instructions.clone();
-        let mut program = Program::new(instructions);
+    fn run(&self, instructions: &[i32]) -> Result<i32, Box<dyn Error>> {
+        let node_count = self.phases.len();
+        for &(from, to) in &self.edges {
+            if from >= node_count || to >= node_count {
+                Err(format!("edge {} -> {} references a missing node", from, to))?
+            }
+        }
+        if self.output >= node_count {
+            Err(format!("output node {} is missing", self.output))?
+        }
+
+        let mut queues: Vec<VecDeque<i32>> = self
+            .phases
+            .iter()
+            .map(|&phase| std::iter::once(phase).collect())
+            .collect();
+        for &(node, value) in &self.inputs {
+            queues
+                .get_mut(node)
+                .ok_or_else(|| format!("input node {} is missing", node))?
+                .push_back(value);
+        }
 
-        program.run(Some(x))?;
-        let output = program.run(Some(previous_output))?;
+        let mut programs = Vec::new();
+        let mut states = Vec::new();
+        for _ in 0..node_count {
+            let mut program = Program::new(instructions.to_vec());
+            states.push(program.run(None)?);
+            programs.push(program);
+        }
+
+        let mut signal = None;
+        loop {
+            let mut progress = false;
+
+            for node in 0..node_count {
+                match states[node] {
+                    Output::WaitingForInput => {
+                        if let Some(input) = queues[node].pop_front() {
+                            states[node] = programs[node].run(Some(input))?;
+                            progress = true;
+                        }
+                    }
+                    Output::Value(value) => {
+                        for &(_, to) in self.edges.iter().filter(|(from, _)| *from == node) {
+                            queues[to].push_back(value);
+                        }
+                        if node == self.output {
+                            signal = Some(value);
+                        }
+                        states[node] = programs[node].run(None)?;
+                        progress = true;
+                    }
+                    Output::Halted => (),
+                }
+            }
 
-        if let Output::Value(value) = output {
-            previous_output = value;
+            let stopped = match self.stop {
+                Stop::AllHalted => states.iter().all(|state| *state == Output::Halted),
+                Stop::OutputHalted => states[self.output] == Output::Halted,
+            };
+            if stopped {
+                break;
+            }
+            if !progress {
+                Err("amplifiers deadlocked waiting for input")?
+            }
         }
+
+        Ok(signal.ok_or("no result")?)
     }
-    Ok(previous_output)
 }
 
 struct Program {
@@ -122,28 +241,20 @@ impl Program {
         }
     }
 
-    fn get_opcode(&mut self) -> ([u8; 3], i32) {
-        let opcode: i32 = self.instructions[self.idx];
-        self.idx += 1;
-
-        let mut reversed_digits = opcode.digits().rev().skip(2);
-
-        let mode_1: u8 = reversed_digits.next().unwrap_or(0);
-        let mode_2: u8 = reversed_digits.next().unwrap_or(0);
-        let mode_3: u8 = reversed_digits.next().unwrap_or(0);
-
-        let opcode = opcode % 100;
-
-        ([mode_1, mode_2, mode_3], opcode)
-    }
-
-    fn get_value(&mut self, mode: u8) -> Result<i32, Box<dyn Error>> {
-        let param = Parameter::new(mode, self.instructions[self.idx])?;
+    fn get_value(&mut self, operand: Operand) -> Result<i32, Box<dyn Error>> {
+        let param = match operand {
+            Operand::Read(mode) => Parameter::new(mode, self.instructions[self.idx])?,
+            Operand::Write(_) => Err("expected read operand")?,
+        };
         self.idx += 1;
         Ok(param.get_value(&self.instructions))
     }
-    fn get_address(&mut self) -> Result<usize, Box<dyn Error>> {
-        let target = usize::try_from(self.instructions[self.idx])?;
+    fn get_address(&mut self, operand: Operand) -> Result<usize, Box<dyn Error>> {
+        let target = match operand {
+            Operand::Write(Mode::Position) => usize::try_from(self.instructions[self.idx])?,
+            Operand::Write(mode) => Err(format!("unsupported write mode: {:?}", mode))?,
+            Operand::Read(_) => Err("expected write operand")?,
+        };
         self.idx += 1;
         Ok(target)
     }
@@ -152,53 +263,55 @@ impl Program {
         while self.idx < self.instructions.len() {
             let instruction_start_i = self.idx;
 
-            let ([mode_1, mode_2, _mode_3], opcode) = self.get_opcode();
-            match opcode {
-                1 => {
-                    let value_1 = self.get_value(mode_1)?;
-                    let value_2 = self.get_value(mode_2)?;
-                    let target = self.get_address()?;
+            let instruction = Instruction::decode(i64::from(self.instructions[self.idx]))?;
+            let operands = &instruction.operands;
+            self.idx += 1;
+            match instruction.opcode {
+                Opcode::Add => {
+                    let value_1 = self.get_value(operands[0])?;
+                    let value_2 = self.get_value(operands[1])?;
+                    let target = self.get_address(operands[2])?;
                     self.instructions[target] = value_1 + value_2;
                 }
-                2 => {
-                    let value_1 = self.get_value(mode_1)?;
-                    let value_2 = self.get_value(mode_2)?;
-                    let target = self.get_address()?;
+                Opcode::Multiply => {
+                    let value_1 = self.get_value(operands[0])?;
+                    let value_2 = self.get_value(operands[1])?;
+                    let target = self.get_address(operands[2])?;
                     self.instructions[target] = value_1 * value_2;
                 }
-                3 => {
+                Opcode::Input => {
                     if let Some(input_value) = input.take() {
-                        let target = self.get_address()?;
+                        let target = self.get_address(operands[0])?;
                         self.instructions[target] = input_value;
                     } else {
                         self.idx = instruction_start_i;
                         return Ok(Output::WaitingForInput);
                     }
                 }
-                4 => {
-                    let value_1 = self.get_value(mode_1)?;
+                Opcode::Output => {
+                    let value_1 = self.get_value(operands[0])?;
                     return Ok(Output::Value(value_1));
                 }
-                5 => {
-                    let value_1 = self.get_value(mode_1)?;
-                    let value_2 = self.get_value(mode_2)?;
+                Opcode::JumpIfTrue => {
+                    let value_1 = self.get_value(operands[0])?;
+                    let value_2 = self.get_value(operands[1])?;
 
                     if value_1 != 0 {
                         self.idx = usize::try_from(value_2)?;
                     }
                 }
-                6 => {
-                    let value_1 = self.get_value(mode_1)?;
-                    let value_2 = self.get_value(mode_2)?;
+                Opcode::JumpIfFalse => {
+                    let value_1 = self.get_value(operands[0])?;
+                    let value_2 = self.get_value(operands[1])?;
 
                     if value_1 == 0 {
                         self.idx = usize::try_from(value_2)?;
                     }
                 }
-                7 => {
-                    let value_1 = self.get_value(mode_1)?;
-                    let value_2 = self.get_value(mode_2)?;
-                    let target = self.get_address()?;
+                Opcode::LessThan => {
+                    let value_1 = self.get_value(operands[0])?;
+                    let value_2 = self.get_value(operands[1])?;
+                    let target = self.get_address(operands[2])?;
 
                     if value_1 < value_2 {
                         self.instructions[target] = 1;
@@ -206,10 +319,10 @@ impl Program {
                         self.instructions[target] = 0;
                     }
                 }
-                8 => {
-                    let value_1 = self.get_value(mode_1)?;
-                    let value_2 = self.get_value(mode_2)?;
-                    let target = self.get_address()?;
+                Opcode::Equals => {
+                    let value_1 = self.get_value(operands[0])?;
+                    let value_2 = self.get_value(operands[1])?;
+                    let target = self.get_address(operands[2])?;
 
                     if value_1 == value_2 {
                         self.instructions[target] = 1;
@@ -218,10 +331,10 @@ impl Program {
                     }
                 }
 
-                99 => {
+                Opcode::Halt => {
                     return Ok(Output::Halted);
                 }
-                x => Err(format!("illegal opcode: {}", x))?,
+                Opcode::AdjustRelativeBase => Err("relative base is not supported")?,
             }
         }
 
@@ -229,7 +342,7 @@ impl Program {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 enum Output {
     Value(i32),
     WaitingForInput,
@@ -282,4 +395,67 @@ mod tests {
         let res = run_looped_amplifier_sequence(&instructions, &sequence).unwrap();
         assert_eq!(res, 139629729);
     }
+
+    #[test]
+    fn test_search_phases() {
+        let instructions = vec![
+            3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0,
+        ];
+        let search = search_phases(&instructions, 0..=4, 5, run_amplifier_sequence);
+        assert_eq!(search.best, Some((vec![4, 3, 2, 1, 0], 43210)));
+        assert_eq!(search.best().unwrap(), (&[4, 3, 2, 1, 0][..], 43210));
+        assert!(search.errors.is_empty());
+    }
+
+    #[test]
+    fn test_search_looped_phases() {
+        let instructions = vec![
+            3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1,
+            28, 1005, 28, 6, 99, 0, 0, 5,
+        ];
+        let search = search_phases(&instructions, 5..=9, 5, run_looped_amplifier_sequence);
+        assert_eq!(search.best, Some((vec![9, 8, 7, 6, 5], 139629729)));
+    }
+
+    #[test]
+    fn test_search_phases_reports_errors() {
+        let instructions = vec![3, 7, 3, 8, 42, 99, 0, 0, 0];
+        let search = search_phases(&instructions, 0..=2, 2, run_amplifier_sequence);
+        assert_eq!(search.best, None);
+        assert!(search.best().is_err());
+        assert_eq!(search.errors.len(), 6);
+        assert_eq!(search.errors[0].0, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_fan_out_fan_in() {
+        // reads a phase and two signals, outputs their sum
+        let instructions = vec![
+            3, 20, 3, 21, 3, 22, 1, 20, 21, 20, 1, 20, 22, 20, 4, 20, 99, 0, 0, 0, 0, 0, 0,
+        ];
+        let topology = Topology {
+            phases: vec![1, 2, 3, 4],
+            edges: vec![(0, 1), (0, 2), (1, 3), (2, 3)],
+            inputs: vec![(0, 0), (0, 0), (1, 10), (2, 100)],
+            output: 3,
+            stop: Stop::OutputHalted,
+        };
+        assert_eq!(topology.run(&instructions).unwrap(), 121);
+    }
+
+    #[test]
+    fn test_deadlock() {
+        let instructions = vec![
+            3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0,
+        ];
+        let mut topology = Topology::chain(&[0, 1]);
+        topology.inputs.clear();
+        assert!(topology.run(&instructions).is_err());
+    }
+
+    #[test]
+    fn test_immediate_write_is_rejected() {
+        let mut program = Program::new(vec![11101, 1, 2, 5, 99, 0]);
+        assert!(program.run(None).is_err());
+    }
 }
@@ -1,26 +1,7 @@
-use digits_iterator::*;
-use itertools::Itertools;
-use std::collections::{HashMap, VecDeque};
-
-use std::convert::TryFrom;
+use crate::intcode::Program;
+use std::collections::VecDeque;
 use std::error::Error;
 
-enum Parameter {
-    Position(usize),
-    Immediate(i64),
-    Relative(i64),
-}
-impl Parameter {
-    fn new(mode: u8, value: i64) -> Result<Parameter, Box<dyn Error>> {
-        match mode {
-            0 => Ok(Parameter::Position(usize::try_from(value)?)),
-            1 => Ok(Parameter::Immediate(value)),
-            2 => Ok(Parameter::Relative(value)),
-            _ => Err(format!("illegal mode: {}", mode))?,
-        }
-    }
-}
-
 pub fn main() -> Result<(), Box<dyn Error>> {
     let instructions = vec![
         1102, 34463338, 34463338, 63, 1007, 63, 34463338, 63, 1005, 63, 53, 1101, 0, 3, 1000, 109,
@@ -93,207 +74,11 @@ pub fn main() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
-pub struct Program {
-    instructions: Vec<i64>,
-    extra_memory: HashMap<usize, i64>,
-    idx: usize,
-    relative_base: i64,
-}
-
-impl Program {
-    pub fn new(instructions: Vec<i64>) -> Self {
-        Program {
-            instructions,
-            extra_memory: HashMap::new(),
-            idx: 0,
-            relative_base: 0,
-        }
-    }
-
-    fn get_value(&mut self, mode: u8) -> Result<i64, Box<dyn Error>> {
-        let param = Parameter::new(mode, self.instructions[self.idx])?;
-        self.idx += 1;
-
-        let value = match param {
-            Parameter::Position(i) => {
-                if i < self.instructions.len() {
-                    self.instructions[i]
-                } else {
-                    self.extra_memory.get(&i).copied().unwrap_or(0)
-                }
-            }
-            Parameter::Immediate(value) => value,
-            Parameter::Relative(i) => {
-                let i = ((i as i64) + self.relative_base) as usize;
-                if i < self.instructions.len() {
-                    self.instructions[i]
-                } else {
-                    self.extra_memory.get(&i).copied().unwrap_or(0)
-                }
-            }
-        };
-
-        Ok(value)
-    }
-
-    fn write_value(&mut self, address: usize, value: i64) {
-        if address < self.instructions.len() {
-            self.instructions[address] = value;
-        } else {
-            let written_value = self.extra_memory.entry(address).or_insert(0);
-            *written_value = value;
-        }
-    }
-
-    fn get_opcode(&mut self) -> ([u8; 3], i64) {
-        let opcode: i64 = self.instructions[self.idx];
-        self.idx += 1;
-
-        let mut reversed_digits = opcode.digits().rev().skip(2);
-
-        let mode_1: u8 = reversed_digits.next().unwrap_or(0);
-        let mode_2: u8 = reversed_digits.next().unwrap_or(0);
-        let mode_3: u8 = reversed_digits.next().unwrap_or(0);
-
-        let opcode = opcode % 100;
-
-        ([mode_1, mode_2, mode_3], opcode)
-    }
-
-    fn get_address(&mut self, mode: u8) -> Result<usize, Box<dyn Error>> {
-        let param = Parameter::new(mode, self.instructions[self.idx])?;
-        self.idx += 1;
-
-        let address = match param {
-            Parameter::Position(i) => i,
-            Parameter::Immediate(_) => Err("illegal mode for address param")?,
-            Parameter::Relative(i) => usize::try_from(self.relative_base + i)?,
-        };
-        Ok(address)
-    }
-
-    pub fn run_all(&mut self, mut inputs: VecDeque<i64>) -> Result<Vec<i64>, Box<dyn Error>> {
-        let mut outputs = Vec::new();
-        let mut input: Option<i64> = inputs.pop_front();
-        loop {
-            match self.run(input)? {
-                Output::WaitingForInput => {
-                    if inputs.is_empty() {
-                        Err("missing input")?
-                    } else {
-                        input = inputs.pop_front()
-                    }
-                }
-                Output::Halted => return Ok(outputs),
-                Output::Value(value) => outputs.push(value),
-            }
-        }
-    }
-
-    pub fn run(&mut self, mut input: Option<i64>) -> Result<Output, Box<dyn Error>> {
-        while self.idx < self.instructions.len() {
-            let instruction_start_i = self.idx;
-
-            let ([mode_1, mode_2, mode_3], opcode) = self.get_opcode();
-            match opcode {
-                1 => {
-                    let value_1 = self.get_value(mode_1)?;
-                    let value_2 = self.get_value(mode_2)?;
-                    let target = self.get_address(mode_3)?;
-                    self.write_value(target, value_1 + value_2);
-                }
-                2 => {
-                    let value_1 = self.get_value(mode_1)?;
-                    let value_2 = self.get_value(mode_2)?;
-                    let target = self.get_address(mode_3)?;
-                    self.write_value(target, value_1 * value_2);
-                }
-                3 => {
-                    if let Some(input_value) = input.take() {
-                        let target = self.get_address(mode_1)?;
-                        self.write_value(target, input_value);
-                    } else {
-                        self.idx = instruction_start_i;
-                        return Ok(Output::WaitingForInput);
-                    }
-                }
-                4 => {
-                    let value_1 = self.get_value(mode_1)?;
-                    return Ok(Output::Value(value_1));
-                }
-                5 => {
-                    let value_1 = self.get_value(mode_1)?;
-                    let value_2 = self.get_value(mode_2)?;
-
-                    if value_1 != 0 {
-                        self.idx = usize::try_from(value_2)?;
-                    }
-                }
-                6 => {
-                    let value_1 = self.get_value(mode_1)?;
-                    let value_2 = self.get_value(mode_2)?;
-
-                    if value_1 == 0 {
-                        self.idx = usize::try_from(value_2)?;
-                    }
-                }
-                7 => {
-                    let value_1 = self.get_value(mode_1)?;
-                    let value_2 = self.get_value(mode_2)?;
-                    let target = self.get_address(mode_3)?;
-
-                    if value_1 < value_2 {
-                        self.write_value(target, 1);
-                    } else {
-                        self.write_value(target, 0);
-                    }
-                }
-                8 => {
-                    let value_1 = self.get_value(mode_1)?;
-                    let value_2 = self.get_value(mode_2)?;
-                    let target = self.get_address(mode_3)?;
-
-                    if value_1 == value_2 {
-                        self.write_value(target, 1);
-                    } else {
-                        self.write_value(target, 0);
-                    }
-                }
-                9 => {
-                    let value_1 = self.get_value(mode_1)?;
-                    self.relative_base += value_1;
-                }
-
-                99 => {
-                    return Ok(Output::Halted);
-                }
-                x => Err(format!("illegal opcode: {}", x))?,
-            }
-        }
-
-        Err("unexpected end")?
-    }
-}
-
-#[derive(Debug)]
-pub enum Output {
-    Value(i64),
-    WaitingForInput,
-    Halted,
-}
-impl Output {
-    pub fn get_value(&self) -> Option<i64> {
-        if let Output::Value(value) = self {
-            Some(*value)
-        } else {
-            None
-        }
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::intcode::Output;
+    use digits_iterator::*;
 
     #[test]
     fn test_day09_1() {
@@ -1,189 +1,171 @@
-use itertools::Itertools;
+use crate::geometry::{self, Clockwise};
+use crate::grid::Grid;
 use num::rational::Ratio;
-use std::collections::{HashSet, VecDeque};
-use std::convert::TryFrom;
+use rayon::prelude::*;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
 use std::error::Error;
-use std::ops::{Add, Sub};
 
-#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone)]
-struct Point {
-    x: i32,
-    y: i32,
-}
-
-impl Sub for Point {
-    type Output = Point;
+type Point = geometry::Point<i64>;
 
-    fn sub(self, other: Point) -> Point {
-        Point {
-            x: self.x - other.x,
-            y: self.y - other.y,
+// Offsets that share a primitive direction lie on the same line of sight.
+fn primitive_direction(offset: Point) -> Point {
+    match (offset.x, offset.y) {
+        (0, 0) => offset,
+        (x, 0) => Point::new(x.signum(), 0),
+        (x, y) => {
+            let ratio = Ratio::new(x, y);
+            let x = ratio.numer().abs() * x.signum();
+            let y = ratio.denom().abs() * y.signum();
+            Point::new(x, y)
         }
     }
 }
 
-impl Add for Point {
-    type Output = Point;
-
-    fn add(self, other: Point) -> Point {
-        Point {
-            x: self.x + other.x,
-            y: self.y + other.y,
-        }
-    }
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Visibility {
+    pub station: Point,
+    pub visible: usize,
+    pub occluded: Vec<Point>,
 }
 
-impl Point {
-    fn straight_line_to(&self, target: &Point) -> StraightLinePointsIter {
-        StraightLinePointsIter {
-            current: *self,
-            target: *target,
+// Only the nearest asteroid in each direction can be seen; it hides the
+// rest, so a single pass over the other asteroids is enough.
+pub fn visibility(station: Point, asteroids: &[Point]) -> Visibility {
+    let mut nearest: HashMap<Point, Point> = HashMap::new();
+    let mut occluded = Vec::new();
+    for &asteroid in asteroids {
+        let offset = asteroid - station;
+        if offset == Point::origin() {
+            continue;
         }
-    }
-    fn has_line_of_sight_to(&self, target: &Point, obstructions: &HashSet<Point>) -> bool {
-        !self
-            .straight_line_to(target)
-            .any(|p| obstructions.contains(&p))
-    }
-}
-
-pub fn main() -> Result<(), Box<dyn Error>> {
-    let input = std::fs::read_to_string("day10/input.txt")?;
-
-    let mut asteroids: HashSet<Point> = HashSet::new();
-    for (y, line) in input.lines().enumerate() {
-        for (x, value) in line.chars().enumerate() {
-            if value == '#' {
-                let x = i32::try_from(x)?;
-                let y = i32::try_from(y)?;
-                let Point = Point { x, y };
-                asteroids.insert(Point);
+        match nearest.entry(primitive_direction(offset)) {
+            Entry::Vacant(entry) => {
+                entry.insert(asteroid);
+            }
+            Entry::Occupied(mut entry) => {
+                let closest = entry.get_mut();
+                if offset.manhattan_length() < (*closest - station).manhattan_length() {
+                    occluded.push(std::mem::replace(closest, asteroid));
+                } else {
+                    occluded.push(asteroid);
+                }
             }
         }
     }
 
-    let asteroids: HashSet<Point> = asteroids;
-    let (part1_ans, &station_point) = asteroids
-        .iter()
-        .map(|asteroid| {
-            let in_sight = asteroids
-                .iter()
-                .filter(|&x| x != asteroid)
-                .filter(|x| asteroid.has_line_of_sight_to(x, &asteroids))
-                .count();
-            (in_sight, asteroid)
-        })
-        .max_by(|(in_sight_1, _), (in_sight_2, _)| in_sight_1.cmp(in_sight_2))
-        .unwrap();
+    Visibility {
+        station,
+        visible: nearest.len(),
+        occluded,
+    }
+}
 
-    println!("{:?}", part1_ans);
-    println!("{:?}", station_point);
+pub fn visibility_map(asteroids: &[Point]) -> Vec<Visibility> {
+    asteroids
+        .par_iter()
+        .map(|&station| visibility(station, asteroids))
+        .collect()
+}
 
-    let mut asteroids = asteroids;
-    asteroids.remove(&station_point);
-    let mut asteroids: Vec<Point> = asteroids.iter().copied().collect::<Vec<_>>();
-    asteroids.sort_unstable_by(|a, b| clockwise_ordering(station_point, a, b));
-
-    let mut clockwise_groups = asteroids
-        .iter()
-        .copied()
-        .map(|p| p - station_point)
-        .group_by(|p: &Point| match (p.x, p.y) {
-            (0, 0) => (0, 0),
-            (x, 0) => (x.signum(), 0),
-            (x, y) => {
-                let ratio = Ratio::new(x, y);
-                let x = ratio.numer().abs() * x.signum();
-                let y = ratio.denom().abs() * y.signum();
-                (x, y)
-            }
-        })
+pub fn best_station(asteroids: &[Point]) -> Option<Visibility> {
+    visibility_map(asteroids)
         .into_iter()
-        .map(|(key, group)| {
-            let mut group: Vec<Point> = group.collect();
-            group.sort_unstable_by(|a, b| (a.x * a.x + a.y * a.y).cmp(&(b.x * b.x + b.y * b.y)));
-            group.into_iter().collect()
-        })
-        .collect::<Vec<VecDeque<Point>>>();
-
-    let mut destroyed_asteroids = 0;
-    let mut asteroid_200: Option<Point> = None;
-    for group in clockwise_groups.iter_mut() {
-        if let Some(asteroid) = group.pop_front() {
-            destroyed_asteroids += 1;
-            if destroyed_asteroids == 200 {
-                asteroid_200.replace(asteroid);
-                println!("asteroid 200: {:?}", asteroid);
+        .max_by_key(|visibility| visibility.visible)
+}
+
+// Yields (index, asteroid, rotation) with the index and rotation counted from
+// one, matching how the puzzle numbers vaporizations.
+pub struct VaporizationOrder {
+    // directions in clockwise order, each with its asteroids nearest first
+    groups: Vec<VecDeque<Point>>,
+    next_group: usize,
+    rotation: usize,
+    vaporized: usize,
+}
+
+impl VaporizationOrder {
+    pub fn new(station: Point, asteroids: &[Point]) -> VaporizationOrder {
+        let mut directions: HashMap<Point, Vec<Point>> = HashMap::new();
+        for &asteroid in asteroids {
+            let offset = asteroid - station;
+            if offset != Point::origin() {
+                directions
+                    .entry(primitive_direction(offset))
+                    .or_default()
+                    .push(asteroid);
             }
         }
-    }
 
-    let part2_ans = {
-        let p = asteroid_200.unwrap() + station_point;
-        p.x * 100 + p.y
-    };
+        let mut directions = directions.into_iter().collect::<Vec<_>>();
+        directions.sort_unstable_by_key(|&(direction, _)| Clockwise(direction));
+        let groups = directions
+            .into_iter()
+            .map(|(_, mut group)| {
+                group.sort_unstable_by_key(|&asteroid| (asteroid - station).manhattan_length());
+                group.into()
+            })
+            .collect::<Vec<_>>();
 
-    println!("{}", part2_ans);
-
-    Ok(())
+        VaporizationOrder {
+            next_group: groups.len(),
+            groups,
+            rotation: 0,
+            vaporized: 0,
+        }
+    }
 }
 
-fn clockwise_ordering(center: Point, a: &Point, b: &Point) -> std::cmp::Ordering {
-    let ref a = *a - center;
-    let ref b = *b - center;
+impl Iterator for VaporizationOrder {
+    type Item = (usize, Point, usize);
 
-    clockwise_angle(a).partial_cmp(&clockwise_angle(b)).unwrap()
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next_group == self.groups.len() {
+            // every group still left gets exactly one shot per rotation
+            self.groups.retain(|group| !group.is_empty());
+            if self.groups.is_empty() {
+                return None;
+            }
+            self.next_group = 0;
+            self.rotation += 1;
+        }
+
+        let asteroid = self.groups[self.next_group].pop_front()?;
+        self.next_group += 1;
+        self.vaporized += 1;
+        Some((self.vaporized, asteroid, self.rotation))
+    }
 }
-fn clockwise_angle(p: &Point) -> f64 {
-    let x = f64::from(p.x);
-    let y = f64::from(-p.y);
 
-    let two_pi = 2.0 * std::f64::consts::PI;
+pub fn main() -> Result<(), Box<dyn Error>> {
+    let input = std::fs::read_to_string("day10/input.txt")?;
 
-    (x.atan2(y) + two_pi) % two_pi
-}
+    let asteroids = Grid::parse(&input, |c| Some(c).filter(|&c| c == '#'))?;
 
-struct StraightLinePointsIter {
-    current: Point,
-    target: Point,
-}
-impl Iterator for StraightLinePointsIter {
-    type Item = Point;
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.current == self.target {
-            return None;
-        }
+    let asteroids: Vec<Point> = asteroids.positions().collect();
+    let best = best_station(&asteroids).ok_or("no asteroids")?;
+    let station_point = best.station;
 
-        let x_dist = self.target.x - self.current.x;
-        let y_dist = self.target.y - self.current.y;
+    println!("{:?}", best.visible);
+    println!("{:?}", station_point);
 
-        if y_dist == 0 {
-            let x_step = x_dist.signum();
-            self.current.x += x_step;
-        } else if x_dist == 0 {
-            let y_step = y_dist.signum();
-            self.current.y += y_step;
-        } else {
-            let ratio = Ratio::new(x_dist, y_dist);
+    let (_, asteroid_200, _) = VaporizationOrder::new(station_point, &asteroids)
+        .nth(199)
+        .ok_or("fewer than 200 asteroids to vaporize")?;
+    println!("asteroid 200: {:?}", asteroid_200);
 
-            let x_step = ratio.numer();
-            let y_step = ratio.denom();
+    let part2_ans = asteroid_200.x * 100 + asteroid_200.y;
 
-            self.current.x += x_step.abs() * x_dist.signum();
-            self.current.y += y_step.abs() * y_dist.signum();
-        }
+    println!("{}", part2_ans);
 
-        if self.current == self.target {
-            None
-        } else {
-            Some(self.current)
-        }
-    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_rng::XorShift;
+    use std::collections::HashSet;
 
     #[test]
     fn test_clockwise_ordering() {
@@ -199,7 +181,7 @@ mod tests {
             Point { x: 1, y: -1 },
         ];
 
-        points.sort_unstable_by(|a, b| clockwise_ordering(center, a, b));
+        points.sort_unstable_by_key(|&point| Clockwise(point - center));
 
         let sorted = vec![
             Point { x: 0, y: -1 },
@@ -214,4 +196,178 @@ mod tests {
 
         assert_eq!(points, sorted);
     }
+
+    const SMALL: &str = ".#..#\n.....\n#####\n....#\n...##\n";
+
+    const LARGE: &str = "\
+.#..##.###...#######
+##.############..##.
+.#.######.########.#
+.###.#######.####.#.
+#####.##.#.##.###.##
+..#####..#.#########
+####################
+#.####....###.#.#.##
+##.#################
+#####.##.###..####..
+..######..##.#######
+####.##.####...##..#
+.#####..#.######.###
+##...#.##########...
+#.##########.#######
+.####.#.###.###.#.##
+....##.##.###..#####
+.#.#.###########.###
+#.#.#.#####.####.###
+###.##.####.##.#..##
+";
+
+    fn parse(input: &str) -> Vec<Point> {
+        Grid::parse(input, |c| Some(c).filter(|&c| c == '#'))
+            .unwrap()
+            .positions()
+            .collect()
+    }
+
+    #[test]
+    fn test_visibility_map() {
+        let asteroids = parse(SMALL);
+        let counts = visibility_map(&asteroids)
+            .iter()
+            .map(|visibility| visibility.visible)
+            .collect::<Vec<_>>();
+        assert_eq!(counts, vec![7, 7, 6, 7, 7, 7, 5, 7, 8, 7]);
+
+        let best = best_station(&asteroids).unwrap();
+        assert_eq!(best.station, Point::new(3, 4));
+        assert_eq!(best.occluded, vec![Point::new(1, 0)]);
+
+        let best = best_station(&parse(LARGE)).unwrap();
+        assert_eq!((best.station, best.visible), (Point::new(11, 13), 210));
+        assert_eq!(best_station(&[]), None);
+    }
+
+    #[test]
+    fn test_vaporization_order() {
+        let asteroids = parse(LARGE);
+        let station = Point::new(11, 13);
+        let order = VaporizationOrder::new(station, &asteroids).collect::<Vec<_>>();
+        assert_eq!(order.len(), asteroids.len() - 1);
+
+        let expected = [
+            (1, Point::new(11, 12)),
+            (2, Point::new(12, 1)),
+            (3, Point::new(12, 2)),
+            (10, Point::new(12, 8)),
+            (20, Point::new(16, 0)),
+            (50, Point::new(16, 9)),
+            (100, Point::new(10, 16)),
+            (199, Point::new(9, 6)),
+            (200, Point::new(8, 2)),
+            (201, Point::new(10, 9)),
+            (299, Point::new(11, 1)),
+        ];
+        for &(index, asteroid) in &expected {
+            assert_eq!((order[index - 1].0, order[index - 1].1), (index, asteroid));
+        }
+        assert_eq!(order[0].2, 1);
+        assert!(order[order.len() - 1].2 > 1);
+    }
+
+    #[test]
+    fn test_vaporization_rotations() {
+        let station = Point::new(0, 0);
+        let asteroids = [
+            Point::new(0, -2),
+            Point::new(0, 0),
+            Point::new(1, 0),
+            Point::new(0, -1),
+            Point::new(0, -3),
+            Point::new(-2, -2),
+        ];
+        let order = VaporizationOrder::new(station, &asteroids).collect::<Vec<_>>();
+        assert_eq!(
+            order,
+            vec![
+                (1, Point::new(0, -1), 1),
+                (2, Point::new(1, 0), 1),
+                (3, Point::new(-2, -2), 1),
+                (4, Point::new(0, -2), 2),
+                (5, Point::new(0, -3), 3),
+            ]
+        );
+
+        // far fewer than 200 visible at once, but every asteroid still goes
+        let mut rng = XorShift(0x9e37_79b9_7f4a_7c15);
+        let asteroids = (0..400)
+            .map(|_| Point::new(rng.below(41) as i64 - 20, rng.below(41) as i64 - 20))
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        let others = asteroids.len() - asteroids.contains(&station) as usize;
+        let order = VaporizationOrder::new(station, &asteroids).collect::<Vec<_>>();
+        assert_eq!(order.len(), others);
+        assert_eq!(
+            order
+                .iter()
+                .map(|&(_, p, _)| p)
+                .collect::<HashSet<_>>()
+                .len(),
+            others
+        );
+
+        // an asteroid goes on the rotation after everything in front of it
+        for &(_, asteroid, rotation) in &order {
+            let direction = primitive_direction(asteroid);
+            let in_front = order
+                .iter()
+                .filter(|&&(_, other, _)| {
+                    primitive_direction(other) == direction
+                        && other.manhattan_length() < asteroid.manhattan_length()
+                })
+                .count();
+            assert_eq!(rotation, in_front + 1);
+        }
+        for pair in order.windows(2) {
+            if pair[0].2 == pair[1].2 {
+                assert!(Clockwise(pair[0].1) < Clockwise(pair[1].1));
+            }
+        }
+    }
+
+    #[test]
+    fn test_visibility_matches_line_of_sight() {
+        let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+        for _ in 0..20 {
+            let asteroids = (0..60)
+                .map(|_| Point::new(rng.below(15) as i64, rng.below(15) as i64))
+                .collect::<HashSet<_>>()
+                .into_iter()
+                .collect::<Vec<_>>();
+
+            for &station in &asteroids {
+                let blocked = |target: Point| {
+                    let step = primitive_direction(target - station);
+                    let mut point = station + step;
+                    while point != target {
+                        if asteroids.contains(&point) {
+                            return true;
+                        }
+                        point += step;
+                    }
+                    false
+                };
+                let mut expected = asteroids
+                    .iter()
+                    .copied()
+                    .filter(|&asteroid| asteroid != station && blocked(asteroid))
+                    .collect::<Vec<_>>();
+                let mut visibility = visibility(station, &asteroids);
+                expected.sort();
+                visibility.occluded.sort();
+                assert_eq!(visibility.occluded, expected);
+                assert_eq!(visibility.visible, asteroids.len() - 1 - expected.len());
+            }
+        }
+    }
 }
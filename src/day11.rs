@@ -1,71 +1,65 @@
-use crate::day09;
-use std::collections::HashMap;
+use crate::export::{self, Raster, Rgb};
+use crate::geometry::{self, Direction};
+use crate::grid::Grid;
+use crate::intcode;
+use crate::ocr;
 use std::error::Error;
 
 use std::convert::TryFrom;
 use std::convert::TryInto;
 
 pub fn main() -> Result<(), Box<dyn Error>> {
-    let instructions = get_instructions();
-    let program = day09::Program::new(instructions);
-
-    let mut painted_panels: HashMap<Point, Color> = HashMap::new();
+    let painted_panels = paint_hull(Color::Black)?;
 
-    let mut robot = Robot::new(program);
+    println!("{}", painted_panels.len());
 
-    loop {
-        let current_position = robot.position;
-        let current_panel_color = *painted_panels
-            .get(&current_position)
-            .unwrap_or(&Color::Black);
+    let mut painted_panels = paint_hull(Color::White)?;
+    painted_panels.retain(|_, color| *color == Color::White);
 
-        let color_to_paint: Color = match robot.program.run(Some(current_panel_color as i64))? {
-            day09::Output::Value(color_value) => color_value.try_into()?,
-            day09::Output::WaitingForInput => panic!(),
-            day09::Output::Halted => break,
-        };
+    let hull = painted_panels.render(|_, color| match color {
+        Some(Color::White) => '█',
+        _ => ' ',
+    });
+    print!("{}", hull);
 
-        painted_panels.insert(current_position, color_to_paint);
+    let part2_ans = read_registration(&painted_panels)?;
+    println!("{}", part2_ans);
 
-        match robot.program.run(Some(current_panel_color as i64))? {
-            day09::Output::Value(direction_to_turn) => {
-                match direction_to_turn {
-                    0 => robot.turn_left(),
-                    1 => robot.turn_right(),
-                    _ => (),
-                }
-                robot.move_one();
-            }
-            day09::Output::WaitingForInput => panic!(),
-            day09::Output::Halted => break,
-        }
+    if let Some(path) = export::export_path("day11_hull.png")? {
+        let registration = Raster::from_grid(&painted_panels, |color| match color {
+            Some(Color::White) => Rgb::WHITE,
+            _ => Rgb::BLACK,
+        });
+        registration.save(path, 10)?;
     }
 
-    println!("{}", painted_panels.len());
+    Ok(())
+}
 
+fn paint_hull(starting_panel_color: Color) -> Result<Grid<Color>, Box<dyn Error>> {
     let instructions = get_instructions();
-    let program = day09::Program::new(instructions);
+    let program = intcode::Program::new(instructions);
 
-    let mut painted_panels: HashMap<Point, Color> = HashMap::new();
+    let mut painted_panels: Grid<Color> = Grid::sparse();
     let mut robot = Robot::new(program);
-    painted_panels.insert(robot.position, Color::White);
+    painted_panels.insert(robot.position, starting_panel_color);
 
     loop {
         let current_position = robot.position;
         let current_panel_color = *painted_panels
-            .get(&current_position)
+            .get(current_position)
             .unwrap_or(&Color::Black);
 
         let color_to_paint: Color = match robot.program.run(Some(current_panel_color as i64))? {
-            day09::Output::Value(color_value) => color_value.try_into()?,
-            day09::Output::WaitingForInput => panic!(),
-            day09::Output::Halted => break,
+            intcode::Output::Value(color_value) => color_value.try_into()?,
+            intcode::Output::WaitingForInput => panic!(),
+            intcode::Output::Halted => break,
         };
 
         painted_panels.insert(current_position, color_to_paint);
 
         match robot.program.run(Some(current_panel_color as i64))? {
-            day09::Output::Value(direction_to_turn) => {
+            intcode::Output::Value(direction_to_turn) => {
                 match direction_to_turn {
                     0 => robot.turn_left(),
                     1 => robot.turn_right(),
@@ -73,31 +67,23 @@ pub fn main() -> Result<(), Box<dyn Error>> {
                 }
                 robot.move_one();
             }
-            day09::Output::WaitingForInput => panic!(),
-            day09::Output::Halted => break,
+            intcode::Output::WaitingForInput => panic!(),
+            intcode::Output::Halted => break,
         }
     }
 
-    painted_panels.retain(|_, color| *color == Color::White);
-
-    let min_x = painted_panels.keys().map(|p| p.x).min().unwrap();
-    let max_x = painted_panels.keys().map(|p| p.x).max().unwrap();
-    let min_y = painted_panels.keys().map(|p| p.y).min().unwrap();
-    let max_y = painted_panels.keys().map(|p| p.y).max().unwrap();
-
-    for y in min_y..=max_y {
-        for x in min_x..=max_x {
-            let p = Point { x, y };
-            if let Some(Color::White) = painted_panels.get(&p) {
-                print!("█")
-            } else {
-                print!(" ")
-            }
-        }
-        println!("")
-    }
+    Ok(painted_panels)
+}
 
-    Ok(())
+fn read_registration(painted_panels: &Grid<Color>) -> Result<String, Box<dyn Error>> {
+    let bounds = painted_panels
+        .bounds()
+        .ok_or("no panels were painted white")?;
+    Ok(ocr::read_grid(
+        painted_panels,
+        ocr::glyph_origin(bounds),
+        |color| *color == Color::White,
+    )?)
 }
 
 #[derive(Clone, Copy, Eq, PartialEq)]
@@ -117,57 +103,30 @@ impl TryFrom<i64> for Color {
     }
 }
 
-enum Direction {
-    Up,
-    Right,
-    Down,
-    Left,
-}
-impl Direction {}
-
-#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone)]
-struct Point {
-    x: i32,
-    y: i32,
-}
+type Point = geometry::Point<i64>;
 
 struct Robot {
-    program: day09::Program,
+    program: intcode::Program,
     position: Point,
     direction: Direction,
 }
 impl Robot {
-    fn new(program: day09::Program) -> Self {
+    fn new(program: intcode::Program) -> Self {
         Robot {
             program,
-            position: Point { x: 0, y: 0 },
+            position: Point::origin(),
             direction: Direction::Up,
         }
     }
     fn turn_left(&mut self) {
-        match self.direction {
-            Direction::Up => self.direction = Direction::Left,
-            Direction::Right => self.direction = Direction::Up,
-            Direction::Down => self.direction = Direction::Right,
-            Direction::Left => self.direction = Direction::Down,
-        }
+        self.direction = self.direction.turn_left();
     }
     fn turn_right(&mut self) {
-        match self.direction {
-            Direction::Up => self.direction = Direction::Right,
-            Direction::Right => self.direction = Direction::Down,
-            Direction::Down => self.direction = Direction::Left,
-            Direction::Left => self.direction = Direction::Up,
-        }
+        self.direction = self.direction.turn_right();
     }
 
     fn move_one(&mut self) {
-        match self.direction {
-            Direction::Up => self.position.y -= 1,
-            Direction::Right => self.position.x += 1,
-            Direction::Down => self.position.y += 1,
-            Direction::Left => self.position.x -= 1,
-        }
+        self.position = self.position.step(self.direction);
     }
 }
 
@@ -847,3 +806,20 @@ fn get_instructions() -> Vec<i64> {
         0,
     ]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_painted_panel_count() {
+        assert_eq!(paint_hull(Color::Black).unwrap().len(), 2054);
+    }
+
+    #[test]
+    fn test_registration() {
+        let mut painted_panels = paint_hull(Color::White).unwrap();
+        painted_panels.retain(|_, color| *color == Color::White);
+        assert_eq!(read_registration(&painted_panels).unwrap(), "KRZEAJHB");
+    }
+}
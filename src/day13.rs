@@ -1,8 +1,10 @@
-use crate::day09::Output;
-use crate::day09::Program;
+use crate::export::{self, Raster, Rgb};
+use crate::geometry;
+use crate::grid::Grid;
+use crate::intcode::Output;
+use crate::intcode::Program;
 use num::FromPrimitive;
 use num_derive::*;
-use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::error::Error;
 
@@ -15,18 +17,14 @@ enum Tile {
     Ball = 4,
 }
 
-#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone)]
-struct Point {
-    x: i64,
-    y: i64,
-}
+type Point = geometry::Point<i64>;
 
 pub fn main() -> Result<(), Box<dyn Error>> {
     let _part1 = {
         let instructions = get_instructions();
         let mut program = Program::new(instructions);
 
-        let mut screen: HashMap<Point, Tile> = HashMap::new();
+        let mut screen: Grid<Tile> = Grid::sparse();
 
         loop {
             let x = match program.run(None)? {
@@ -53,7 +51,7 @@ pub fn main() -> Result<(), Box<dyn Error>> {
 
         let mut program = Program::new(instructions);
 
-        let mut screen: HashMap<Point, Tile> = HashMap::new();
+        let mut screen: Grid<Tile> = Grid::sparse();
         let mut score = 0;
         let mut joystick_position = 0;
         loop {
@@ -102,29 +100,33 @@ pub fn main() -> Result<(), Box<dyn Error>> {
             }
         }
         println!("final score: {}", score);
+
+        if let Some(path) = export::export_path("day13_screen.png")? {
+            Raster::from_grid(&screen, tile_colour).save(path, 10)?;
+        }
     };
 
     Ok(())
 }
 
-fn print_screen(screen: &HashMap<Point, Tile>) {
-    let min_x = screen.keys().map(|p| p.x).min().unwrap();
-    let max_x = screen.keys().map(|p| p.x).max().unwrap();
-    let min_y = screen.keys().map(|p| p.y).min().unwrap();
-    let max_y = screen.keys().map(|p| p.y).max().unwrap();
-
-    for y in min_y..=max_y {
-        for x in min_x..=max_x {
-            let c = match screen.get(&Point { x, y }).unwrap_or(&Tile::Empty) {
-                Tile::Empty => " ",
-                Tile::Wall => "█",
-                Tile::Block => "□",
-                Tile::Paddle => "■",
-                Tile::Ball => "●",
-            };
-            print!("{}", c);
-        }
-        println!("")
+fn print_screen(screen: &Grid<Tile>) {
+    let rendered = screen.render(|_, tile| match tile.unwrap_or(&Tile::Empty) {
+        Tile::Empty => " ",
+        Tile::Wall => "█",
+        Tile::Block => "□",
+        Tile::Paddle => "■",
+        Tile::Ball => "●",
+    });
+    print!("{}", rendered);
+}
+
+fn tile_colour(tile: Option<&Tile>) -> Rgb {
+    match tile.unwrap_or(&Tile::Empty) {
+        Tile::Empty => Rgb::BLACK,
+        Tile::Wall => Rgb(128, 128, 128),
+        Tile::Block => Rgb(200, 80, 40),
+        Tile::Paddle => Rgb::WHITE,
+        Tile::Ball => Rgb(240, 200, 0),
     }
 }
 
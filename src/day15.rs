@@ -1,12 +1,16 @@
-use crate::day09;
+use crate::export::{self, Raster, Rgb};
+use crate::geometry::{self, Direction};
+use crate::grid::Grid;
+use crate::intcode;
 use num_derive::*;
 use num_traits::*;
 use petgraph::graphmap::GraphMap;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
+use std::error::Error;
 
-pub fn main() {
+pub fn main() -> Result<(), Box<dyn Error>> {
     let instructions = get_instructions();
-    let program = day09::Program::new(instructions);
+    let program = intcode::Program::new(instructions);
     let mut droid = Droid::new(program);
 
     droid.move_forward();
@@ -29,17 +33,20 @@ pub fn main() {
         }
     }
     print_map(&droid.map, &droid);
+    if let Some(path) = export::export_path("day15_maze.png")? {
+        Raster::from_grid(&droid.map, tile_colour).save(path, 10)?;
+    }
 
     let mut edges: HashSet<(Point, Point)> = HashSet::new();
-    for (&position, tile) in droid.map.iter() {
-        let east_neighbor = position + (Direction::East * 1);
-        let south_neighbor = position + (Direction::South * 1);
+    for (position, _) in droid.map.iter() {
+        let east_neighbor = position.step(Direction::Right);
+        let south_neighbor = position.step(Direction::Down);
 
-        if let Some(Tile::Floor(_)) = droid.map.get(&east_neighbor) {
+        if let Some(Tile::Floor(_)) = droid.map.get(east_neighbor) {
             edges.insert((position, east_neighbor));
         }
 
-        if let Some(Tile::Floor(_)) = droid.map.get(&south_neighbor) {
+        if let Some(Tile::Floor(_)) = droid.map.get(south_neighbor) {
             edges.insert((position, south_neighbor));
         }
     }
@@ -49,7 +56,7 @@ pub fn main() {
     let oxygen_position: Point = droid
         .map
         .iter()
-        .find_map(|(&position, &tile)| {
+        .find_map(|(position, &tile)| {
             Some(position).filter(|_| tile == Tile::Floor(Some(OxygenSystem)))
         })
         .unwrap();
@@ -66,29 +73,32 @@ pub fn main() {
     let max_distance = distances_from_oxygen.values().max().unwrap();
 
     println!("max distance from oxygen: {}", max_distance); // off by one error for some reason ...
-}
 
-fn print_map(tiles: &HashMap<Point, Tile>, droid: &Droid) {
-    let min_x = tiles.keys().map(|p| p.x).min().unwrap_or(0);
-    let max_x = tiles.keys().map(|p| p.x).max().unwrap_or(0);
-    let min_y = tiles.keys().map(|p| p.y).min().unwrap_or(0);
-    let max_y = tiles.keys().map(|p| p.y).max().unwrap_or(0);
+    Ok(())
+}
 
-    for y in min_y..=max_y {
-        for x in min_x..=max_x {
-            let p = Point { x, y };
-            if p == droid.position {
-                print!("D ")
-            } else {
-                match tiles.get(&p) {
-                    Some(Tile::Wall) => print!("██"),
-                    Some(Tile::Floor(None)) => print!("· "),
-                    Some(Tile::Floor(Some(OxygenSystem))) => print!("X "),
-                    _ => print!("  "),
-                }
+fn print_map(tiles: &Grid<Tile>, droid: &Droid) {
+    let map = tiles.render(|p, tile| {
+        if p == droid.position {
+            "D "
+        } else {
+            match tile {
+                Some(Tile::Wall) => "██",
+                Some(Tile::Floor(None)) => "· ",
+                Some(Tile::Floor(Some(OxygenSystem))) => "X ",
+                None => "  ",
             }
         }
-        println!("")
+    });
+    print!("{}", map);
+}
+
+fn tile_colour(tile: Option<&Tile>) -> Rgb {
+    match tile {
+        Some(Tile::Wall) => Rgb(96, 96, 96),
+        Some(Tile::Floor(None)) => Rgb::WHITE,
+        Some(Tile::Floor(Some(OxygenSystem))) => Rgb(0, 120, 255),
+        None => Rgb::BLACK,
     }
 }
 
@@ -108,34 +118,34 @@ enum Tile {
     Wall,
 }
 struct Droid {
-    program: day09::Program,
+    program: intcode::Program,
     start_position: Point,
     position: Point,
     direction: Direction,
-    map: HashMap<Point, Tile>,
+    map: Grid<Tile>,
 }
 impl Droid {
-    fn new(program: day09::Program) -> Self {
-        let position = Point { x: 0, y: 0 };
+    fn new(program: intcode::Program) -> Self {
+        let position = Point::origin();
 
-        let mut map: HashMap<Point, Tile> = HashMap::new();
+        let mut map: Grid<Tile> = Grid::sparse();
         map.insert(position, Tile::Floor(None));
 
         Droid {
             program,
             start_position: position,
             position,
-            direction: Direction::North,
+            direction: Direction::Up,
             map,
         }
     }
 
     fn move_forward(&mut self) -> Status {
-        let input = self.direction.to_i64().unwrap();
+        let input = movement_command(self.direction);
         let status = self.program.run(Some(input)).unwrap().get_value().unwrap();
         let status = Status::from_i64(status).unwrap();
 
-        let target_position = self.position + (self.direction * 1);
+        let target_position = self.position.step(self.direction);
         if status != Status::HitWall {
             self.position = target_position;
         }
@@ -159,63 +169,21 @@ impl Droid {
     }
 
     fn rotate_left(&mut self) {
-        let left_direction = match self.direction {
-            Direction::North => Direction::West,
-            Direction::South => Direction::East,
-            Direction::West => Direction::South,
-            Direction::East => Direction::North,
-        };
-        self.direction = left_direction;
+        self.direction = self.direction.turn_left();
     }
     fn rotate_right(&mut self) {
-        let right_direction = match self.direction {
-            Direction::North => Direction::East,
-            Direction::South => Direction::West,
-            Direction::West => Direction::North,
-            Direction::East => Direction::South,
-        };
-        self.direction = right_direction;
+        self.direction = self.direction.turn_right();
     }
 }
 
-#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone, PartialOrd, Ord)]
-struct Point {
-    x: i32,
-    y: i32,
-}
-use std::ops::{Add, AddAssign};
-impl Add for Point {
-    type Output = Self;
-    fn add(self, other: Self) -> Self::Output {
-        Point {
-            x: self.x + other.x,
-            y: self.y + other.y,
-        }
-    }
-}
-impl AddAssign for Point {
-    fn add_assign(&mut self, other: Self) {
-        *self = *self + other
-    }
-}
+type Point = geometry::Point<i64>;
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq, FromPrimitive, ToPrimitive)]
-enum Direction {
-    North = 1,
-    South = 2,
-    West = 3,
-    East = 4,
-}
-use std::ops::Mul;
-impl Mul<i32> for Direction {
-    type Output = Point;
-    fn mul(self, rhs: i32) -> Self::Output {
-        match self {
-            Direction::North => Point { x: 0, y: -rhs },
-            Direction::South => Point { x: 0, y: rhs },
-            Direction::West => Point { x: -rhs, y: 0 },
-            Direction::East => Point { x: rhs, y: 0 },
-        }
+fn movement_command(direction: Direction) -> i64 {
+    match direction {
+        Direction::Up => 1,
+        Direction::Down => 2,
+        Direction::Left => 3,
+        Direction::Right => 4,
     }
 }
 
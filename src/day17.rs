@@ -1,54 +1,34 @@
-use crate::day09;
+use crate::geometry::Point;
+use crate::grid::Grid;
+use crate::intcode;
 use std::collections::HashSet;
 use std::error::Error;
 
-#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone)]
-struct Point2D {
-    x: isize,
-    y: isize,
-}
-
 pub fn main() -> Result<(), Box<dyn Error>> {
-    let robot_chars = vec!['^', 'v', '<', '>'];
+    let robot_chars = ['^', 'v', '<', '>'];
     let instructions = get_instructions();
-    let mut program = day09::Program::new(instructions);
-
-    let mut scaffold_points = HashSet::new();
+    let mut program = intcode::Program::new(instructions);
 
     let mut image: String = String::new();
-    let mut x: isize = 0;
-    let mut y: isize = 0;
 
     while let Some(value) = program.run(None)?.get_value() {
         let c = value as u8 as char;
 
         image.push(c);
-
-        if let '^' | 'v' | '<' | '>' | '#' = c {
-            scaffold_points.insert(Point2D { x, y });
-        }
-
-        if c == '\n' {
-            y += 1;
-            x = 0;
-        } else {
-            x += 1;
-        }
     }
 
+    let scaffold_points = Grid::parse(&image, |c| {
+        Some(c).filter(|c| *c == '#' || robot_chars.contains(c))
+    })?;
+
     let intersection_points = scaffold_points
-        .iter()
-        .filter(|&&Point2D { x, y }| {
-            scaffold_points.contains(&Point2D { x: x + 1, y })
-                && scaffold_points.contains(&Point2D { x: x - 1, y })
-                && scaffold_points.contains(&Point2D { x, y: y + 1 })
-                && scaffold_points.contains(&Point2D { x, y: y - 1 })
-        })
+        .positions()
+        .filter(|&point| scaffold_points.neighbours4(point).count() == 4)
         .collect::<HashSet<_>>();
 
-    let part1_ans: isize = intersection_points
+    let part1_ans: i64 = intersection_points
         .iter()
-        .map(|&Point2D { x, y }| x * y)
+        .map(|&Point { x, y }| x * y)
         .sum();
 
     println!("{}", image);
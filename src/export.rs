@@ -0,0 +1,423 @@
+use crate::grid::Grid;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+const EXPORT_DIR: &str = "AOC_EXPORT_DIR";
+
+// Exports are opt-in: nothing is written unless AOC_EXPORT_DIR names a
+// directory, which is created on first use.
+pub fn export_path(name: &str) -> Result<Option<PathBuf>, Box<dyn Error>> {
+    export_path_in(std::env::var_os(EXPORT_DIR).as_deref(), name)
+}
+
+fn export_path_in(dir: Option<&OsStr>, name: &str) -> Result<Option<PathBuf>, Box<dyn Error>> {
+    match dir {
+        Some(dir) => {
+            let dir = PathBuf::from(dir);
+            std::fs::create_dir_all(&dir)?;
+            Ok(Some(dir.join(name)))
+        }
+        None => Ok(None),
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    pub const BLACK: Rgb = Rgb(0, 0, 0);
+    pub const WHITE: Rgb = Rgb(255, 255, 255);
+
+    pub fn hex(self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct Rgba(pub u8, pub u8, pub u8, pub u8);
+
+impl Rgba {
+    pub const TRANSPARENT: Rgba = Rgba(0, 0, 0, 0);
+
+    pub fn opaque(colour: Rgb) -> Rgba {
+        Rgba(colour.0, colour.1, colour.2, 255)
+    }
+
+    pub fn is_transparent(self) -> bool {
+        self.3 == 0
+    }
+
+    // Porter-Duff "over" with straight (non-premultiplied) alpha
+    pub fn over(self, below: Rgba) -> Rgba {
+        let top_alpha = f64::from(self.3) / 255.0;
+        let below_alpha = f64::from(below.3) / 255.0 * (1.0 - top_alpha);
+        let alpha = top_alpha + below_alpha;
+        if alpha == 0.0 {
+            return Rgba::TRANSPARENT;
+        }
+
+        let channel = |top: u8, below: u8| {
+            ((f64::from(top) * top_alpha + f64::from(below) * below_alpha) / alpha).round() as u8
+        };
+        Rgba(
+            channel(self.0, below.0),
+            channel(self.1, below.1),
+            channel(self.2, below.2),
+            (alpha * 255.0).round() as u8,
+        )
+    }
+
+    pub fn flatten(self, background: Rgb) -> Rgb {
+        let Rgba(r, g, b, _) = self.over(Rgba::opaque(background));
+        Rgb(r, g, b)
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Format {
+    Ppm,
+    Png,
+    Svg,
+}
+
+impl Format {
+    pub fn from_path(path: &Path) -> Option<Format> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "ppm" => Some(Format::Ppm),
+            "png" => Some(Format::Png),
+            "svg" => Some(Format::Svg),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Raster {
+    width: usize,
+    height: usize,
+    pixels: Vec<Rgb>,
+}
+
+impl Raster {
+    pub fn from_rows<T>(rows: &[Vec<T>], palette: impl Fn(&T) -> Rgb) -> Raster {
+        let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+        let mut pixels = Vec::with_capacity(width * rows.len());
+        for row in rows {
+            pixels.extend(row.iter().map(&palette));
+            pixels.extend((row.len()..width).map(|_| Rgb::BLACK));
+        }
+
+        Raster {
+            width,
+            height: rows.len(),
+            pixels,
+        }
+    }
+
+    pub fn from_grid<T>(grid: &Grid<T>, palette: impl Fn(Option<&T>) -> Rgb) -> Raster {
+        let rows = grid
+            .rows()
+            .map(|row| row.into_iter().map(|(_, tile)| palette(tile)).collect())
+            .collect::<Vec<Vec<Rgb>>>();
+        Raster::from_rows(&rows, |&colour| colour)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Rgb {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn scaled(&self, scale: usize) -> Raster {
+        let mut pixels = Vec::with_capacity(self.pixels.len() * scale * scale);
+        for row in self.pixels.chunks(self.width.max(1)) {
+            for _ in 0..scale {
+                for &pixel in row {
+                    pixels.extend(std::iter::repeat_n(pixel, scale));
+                }
+            }
+        }
+
+        Raster {
+            width: self.width * scale,
+            height: self.height * scale,
+            pixels,
+        }
+    }
+
+    pub fn write(&self, format: Format, scale: usize, out: &mut impl Write) -> io::Result<()> {
+        if scale == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "scale must be positive",
+            ));
+        }
+        match format {
+            Format::Ppm => self.scaled(scale).write_ppm(out),
+            Format::Png => self.scaled(scale).write_png(out),
+            Format::Svg => self.write_svg(scale, out),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>, scale: usize) -> Result<(), Box<dyn Error>> {
+        let path = path.as_ref();
+        let format = Format::from_path(path)
+            .ok_or_else(|| format!("unknown image format: {}", path.display()))?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write(format, scale, &mut out)?;
+        out.flush()?;
+        Ok(())
+    }
+
+    fn write_ppm(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "P6\n{} {}\n255\n", self.width, self.height)?;
+        for pixel in &self.pixels {
+            out.write_all(&[pixel.0, pixel.1, pixel.2])?;
+        }
+        Ok(())
+    }
+
+    fn write_png(&self, out: &mut impl Write) -> io::Result<()> {
+        if self.width == 0 || self.height == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "empty image"));
+        }
+
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&png_size(self.width)?);
+        header.extend_from_slice(&png_size(self.height)?);
+        // 8 bits per channel, truecolour, default compression/filter, no interlace
+        header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+        let mut scanlines = Vec::with_capacity(self.height * (1 + 3 * self.width));
+        for row in self.pixels.chunks(self.width) {
+            scanlines.push(0);
+            for pixel in row {
+                scanlines.extend_from_slice(&[pixel.0, pixel.1, pixel.2]);
+            }
+        }
+
+        out.write_all(&[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'])?;
+        write_png_chunk(out, b"IHDR", &header)?;
+        write_png_chunk(out, b"IDAT", &zlib_stored(&scanlines))?;
+        write_png_chunk(out, b"IEND", &[])
+    }
+
+    fn write_svg(&self, scale: usize, out: &mut impl Write) -> io::Result<()> {
+        let (width, height) = (self.width * scale, self.height * scale);
+        writeln!(
+            out,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}" shape-rendering="crispEdges">"#,
+            width, height, self.width, self.height
+        )?;
+
+        for (y, row) in self.pixels.chunks(self.width.max(1)).enumerate() {
+            let mut x = 0;
+            while x < row.len() {
+                let run = row[x..]
+                    .iter()
+                    .take_while(|&&pixel| pixel == row[x])
+                    .count();
+                writeln!(
+                    out,
+                    r#"<rect x="{}" y="{}" width="{}" height="1" fill="{}"/>"#,
+                    x,
+                    y,
+                    run,
+                    row[x].hex()
+                )?;
+                x += run;
+            }
+        }
+
+        writeln!(out, "</svg>")
+    }
+}
+
+// PNG stores sizes and chunk lengths as big-endian u32
+fn png_size(size: usize) -> io::Result<[u8; 4]> {
+    let size = u32::try_from(size)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "image too large for PNG"))?;
+    Ok(size.to_be_bytes())
+}
+
+fn write_png_chunk(out: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    out.write_all(&png_size(data.len())?)?;
+    out.write_all(kind)?;
+    out.write_all(data)?;
+    let crc = crc32(kind.iter().chain(data));
+    out.write_all(&crc.to_be_bytes())
+}
+
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    const MAX_BLOCK: usize = 0xffff;
+
+    // deflate, 32K window, no preset dictionary, fastest compression
+    let mut stream = vec![0x78, 0x01];
+    let mut blocks = data.chunks(MAX_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        stream.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let is_final = blocks.peek().is_none();
+        let len = block.len() as u16;
+        stream.push(is_final as u8);
+        stream.extend_from_slice(&len.to_le_bytes());
+        stream.extend_from_slice(&(!len).to_le_bytes());
+        stream.extend_from_slice(block);
+    }
+    stream.extend_from_slice(&adler32(data).to_be_bytes());
+    stream
+}
+
+fn crc32<'a>(bytes: impl IntoIterator<Item = &'a u8>) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    const MOD_ADLER: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in bytes {
+        a = (a + u32::from(byte)) % MOD_ADLER;
+        b = (b + a) % MOD_ADLER;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Point;
+
+    fn checkerboard() -> Raster {
+        let rows = vec![vec![true, false], vec![false, true]];
+        Raster::from_rows(&rows, |&on| if on { Rgb::WHITE } else { Rgb::BLACK })
+    }
+
+    #[test]
+    fn test_checksums() {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+
+    #[test]
+    fn test_scaled() {
+        let raster = checkerboard().scaled(2);
+        assert_eq!((raster.width(), raster.height()), (4, 4));
+        assert_eq!(raster.get(1, 1), Rgb::WHITE);
+        assert_eq!(raster.get(2, 1), Rgb::BLACK);
+        assert_eq!(raster.get(3, 3), Rgb::WHITE);
+    }
+
+    #[test]
+    fn test_ppm() {
+        let mut out = Vec::new();
+        checkerboard().write(Format::Ppm, 1, &mut out).unwrap();
+        let mut expected = b"P6\n2 2\n255\n".to_vec();
+        expected.extend_from_slice(&[255, 255, 255, 0, 0, 0, 0, 0, 0, 255, 255, 255]);
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_png() {
+        let mut out = Vec::new();
+        checkerboard().write(Format::Png, 3, &mut out).unwrap();
+
+        assert_eq!(
+            &out[..8],
+            &[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n']
+        );
+        assert_eq!(&out[12..16], b"IHDR");
+        assert_eq!(&out[16..24], &[0, 0, 0, 6, 0, 0, 0, 6]);
+        assert_eq!(
+            &out[out.len() - 12..],
+            &[0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]
+        );
+
+        let idat_len = u32::from_be_bytes([out[33], out[34], out[35], out[36]]) as usize;
+        assert_eq!(&out[37..41], b"IDAT");
+        let zlib = &out[41..41 + idat_len];
+        let scanlines = &zlib[7..zlib.len() - 4];
+        assert_eq!(scanlines.len(), 6 * (1 + 6 * 3));
+        assert_eq!(&scanlines[..4], &[0, 255, 255, 255]);
+        assert_eq!(&zlib[zlib.len() - 4..], &adler32(scanlines).to_be_bytes());
+    }
+
+    #[test]
+    fn test_png_size_limit() {
+        assert_eq!(png_size(6).unwrap(), [0, 0, 0, 6]);
+        assert!(png_size(u32::MAX as usize).is_ok());
+        assert!(png_size(u32::MAX as usize + 1).is_err());
+    }
+
+    #[test]
+    fn test_svg_merges_runs() {
+        let mut grid = Grid::sparse();
+        grid.insert(Point::new(0, 0), 1);
+        grid.insert(Point::new(3, 0), 1);
+        let raster = Raster::from_grid(&grid, |tile| match tile {
+            Some(_) => Rgb(255, 0, 0),
+            None => Rgb::BLACK,
+        });
+
+        let mut out = Vec::new();
+        raster.write(Format::Svg, 10, &mut out).unwrap();
+        let svg = String::from_utf8(out).unwrap();
+
+        assert!(svg.contains(r#"width="40" height="10" viewBox="0 0 4 1""#));
+        assert!(svg.contains(r##"<rect x="1" y="0" width="2" height="1" fill="#000000"/>"##));
+        assert_eq!(svg.matches("<rect").count(), 3);
+        assert!(svg.trim_end().ends_with("</svg>"));
+    }
+
+    #[test]
+    fn test_rgba_over() {
+        let half_white = Rgba(255, 255, 255, 128);
+        assert_eq!(half_white.flatten(Rgb::BLACK), Rgb(128, 128, 128));
+        assert_eq!(half_white.over(Rgba::TRANSPARENT), half_white);
+        assert_eq!(Rgba::TRANSPARENT.over(half_white), half_white);
+        assert_eq!(Rgba(255, 0, 0, 255).over(half_white), Rgba(255, 0, 0, 255));
+        assert_eq!(half_white.over(half_white).3, 192);
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(Format::from_path(Path::new("hull.PNG")), Some(Format::Png));
+        assert_eq!(Format::from_path(Path::new("maze.svg")), Some(Format::Svg));
+        assert_eq!(Format::from_path(Path::new("screen")), None);
+    }
+
+    #[test]
+    fn test_export_path_is_opt_in() {
+        assert_eq!(export_path_in(None, "maze.png").unwrap(), None);
+
+        let dir = std::env::temp_dir().join("aoc_export_path_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = export_path_in(Some(dir.as_os_str()), "maze.png").unwrap();
+
+        assert_eq!(path, Some(dir.join("maze.png")));
+        assert!(dir.is_dir());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,478 @@
+use num_traits::{One, Signed, ToPrimitive, Zero};
+use std::cmp::Ordering;
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone, PartialOrd, Ord, Default)]
+pub struct Point<T> {
+    pub x: T,
+    pub y: T,
+}
+
+impl<T> Point<T> {
+    pub fn new(x: T, y: T) -> Self {
+        Point { x, y }
+    }
+}
+
+impl<T: Zero> Point<T> {
+    pub fn origin() -> Self {
+        Point {
+            x: T::zero(),
+            y: T::zero(),
+        }
+    }
+}
+
+impl<T: Signed + Copy> Point<T> {
+    pub fn manhattan_distance(self, other: Point<T>) -> T {
+        (self.x - other.x).abs() + (self.y - other.y).abs()
+    }
+
+    pub fn manhattan_length(self) -> T {
+        self.x.abs() + self.y.abs()
+    }
+
+    pub fn step(self, direction: Direction) -> Self {
+        self + direction.offset()
+    }
+
+    pub fn neighbours4(self) -> impl Iterator<Item = Point<T>> {
+        Direction::ALL
+            .iter()
+            .map(move |&direction| self.step(direction))
+    }
+
+    pub fn neighbours8(self) -> impl Iterator<Item = Point<T>> {
+        let steps = [-T::one(), T::zero(), T::one()];
+        let mut neighbours = Vec::with_capacity(8);
+        for &dy in &steps {
+            for &dx in &steps {
+                if !dx.is_zero() || !dy.is_zero() {
+                    neighbours.push(self + Point { x: dx, y: dy });
+                }
+            }
+        }
+        neighbours.into_iter()
+    }
+}
+
+impl<T: Add<Output = T>> Add for Point<T> {
+    type Output = Point<T>;
+    fn add(self, other: Point<T>) -> Point<T> {
+        Point {
+            x: self.x + other.x,
+            y: self.y + other.y,
+        }
+    }
+}
+
+impl<T: Sub<Output = T>> Sub for Point<T> {
+    type Output = Point<T>;
+    fn sub(self, other: Point<T>) -> Point<T> {
+        Point {
+            x: self.x - other.x,
+            y: self.y - other.y,
+        }
+    }
+}
+
+impl<T: Mul<Output = T> + Copy> Mul<T> for Point<T> {
+    type Output = Point<T>;
+    fn mul(self, rhs: T) -> Point<T> {
+        Point {
+            x: self.x * rhs,
+            y: self.y * rhs,
+        }
+    }
+}
+
+impl<T: Neg<Output = T>> Neg for Point<T> {
+    type Output = Point<T>;
+    fn neg(self) -> Point<T> {
+        Point {
+            x: -self.x,
+            y: -self.y,
+        }
+    }
+}
+
+impl<T: Add<Output = T> + Copy> AddAssign for Point<T> {
+    fn add_assign(&mut self, other: Point<T>) {
+        *self = *self + other
+    }
+}
+
+impl<T: Sub<Output = T> + Copy> SubAssign for Point<T> {
+    fn sub_assign(&mut self, other: Point<T>) {
+        *self = *self - other
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone)]
+pub enum Direction {
+    Up,
+    Right,
+    Down,
+    Left,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::Up,
+        Direction::Right,
+        Direction::Down,
+        Direction::Left,
+    ];
+
+    pub fn turn_left(self) -> Direction {
+        match self {
+            Direction::Up => Direction::Left,
+            Direction::Right => Direction::Up,
+            Direction::Down => Direction::Right,
+            Direction::Left => Direction::Down,
+        }
+    }
+
+    pub fn turn_right(self) -> Direction {
+        match self {
+            Direction::Up => Direction::Right,
+            Direction::Right => Direction::Down,
+            Direction::Down => Direction::Left,
+            Direction::Left => Direction::Up,
+        }
+    }
+
+    pub fn opposite(self) -> Direction {
+        self.turn_left().turn_left()
+    }
+
+    pub fn is_horizontal(self) -> bool {
+        match self {
+            Direction::Left | Direction::Right => true,
+            Direction::Up | Direction::Down => false,
+        }
+    }
+
+    pub fn is_vertical(self) -> bool {
+        !self.is_horizontal()
+    }
+
+    // y grows downwards, like rows on the screen
+    pub fn offset<T: Signed>(self) -> Point<T> {
+        match self {
+            Direction::Up => Point::new(T::zero(), -T::one()),
+            Direction::Right => Point::new(T::one(), T::zero()),
+            Direction::Down => Point::new(T::zero(), T::one()),
+            Direction::Left => Point::new(-T::one(), T::zero()),
+        }
+    }
+}
+
+impl<T: Signed + Copy> Mul<T> for Direction {
+    type Output = Point<T>;
+    fn mul(self, rhs: T) -> Point<T> {
+        self.offset() * rhs
+    }
+}
+
+// Orders offsets by their clockwise angle from Up, with y growing downwards.
+// Offsets along the same ray compare equal whatever their length, and the zero
+// offset comes before everything else. Comparisons are exact: a half-plane
+// test, then the sign of the cross product, worked out in i128. Coordinates
+// are limited to i32 and i64, whose products always fit; u64 or i128 ones
+// could overflow.
+#[derive(Debug, Copy, Clone)]
+pub struct Clockwise<T>(pub Point<T>);
+
+mod coord {
+    pub trait SignedCoord: Copy + Into<i128> {}
+
+    impl SignedCoord for i32 {}
+    impl SignedCoord for i64 {}
+}
+
+use coord::SignedCoord;
+
+impl<T: SignedCoord> Clockwise<T> {
+    fn wide(self) -> (i128, i128) {
+        (self.0.x.into(), self.0.y.into())
+    }
+
+    // 1 from Up until just before Down, 2 from Down until just before Up
+    fn half(self) -> u8 {
+        match self.wide() {
+            (0, 0) => 0,
+            (x, y) if x > 0 || (x == 0 && y < 0) => 1,
+            _ => 2,
+        }
+    }
+}
+
+impl<T: SignedCoord> Ord for Clockwise<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let ((ax, ay), (bx, by)) = (self.wide(), other.wide());
+        // within a half-plane a comes first when turning from a to b is clockwise
+        self.half()
+            .cmp(&other.half())
+            .then_with(|| 0.cmp(&(ax * by - ay * bx)))
+    }
+}
+
+impl<T: SignedCoord> PartialOrd for Clockwise<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: SignedCoord> PartialEq for Clockwise<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T: SignedCoord> Eq for Clockwise<T> {}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct BoundingBox<T> {
+    pub min: Point<T>,
+    pub max: Point<T>,
+}
+
+impl<T: Ord + Copy> BoundingBox<T> {
+    pub fn from_point(point: Point<T>) -> Self {
+        BoundingBox {
+            min: point,
+            max: point,
+        }
+    }
+
+    pub fn from_points(points: impl IntoIterator<Item = Point<T>>) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        let mut bounds = BoundingBox::from_point(first);
+        for point in points {
+            bounds.include(point);
+        }
+        Some(bounds)
+    }
+
+    pub fn include(&mut self, point: Point<T>) {
+        self.min.x = self.min.x.min(point.x);
+        self.min.y = self.min.y.min(point.y);
+        self.max.x = self.max.x.max(point.x);
+        self.max.y = self.max.y.max(point.y);
+    }
+
+    pub fn contains(&self, point: Point<T>) -> bool {
+        self.min.x <= point.x
+            && point.x <= self.max.x
+            && self.min.y <= point.y
+            && point.y <= self.max.y
+    }
+
+    pub fn intersection(&self, other: &BoundingBox<T>) -> Option<Self> {
+        let min = Point::new(self.min.x.max(other.min.x), self.min.y.max(other.min.y));
+        let max = Point::new(self.max.x.min(other.max.x), self.max.y.min(other.max.y));
+        if min.x <= max.x && min.y <= max.y {
+            Some(BoundingBox { min, max })
+        } else {
+            None
+        }
+    }
+}
+
+impl<T: Signed + Copy> BoundingBox<T> {
+    pub fn width(&self) -> T {
+        self.max.x - self.min.x + T::one()
+    }
+
+    pub fn height(&self) -> T {
+        self.max.y - self.min.y + T::one()
+    }
+}
+
+impl<T> BoundingBox<T>
+where
+    T: Copy + PartialOrd + One + Add<Output = T> + ToPrimitive,
+{
+    pub fn rows(&self) -> impl Iterator<Item = impl Iterator<Item = Point<T>>> {
+        let (min, max) = (self.min, self.max);
+        num::range_inclusive(min.y, max.y)
+            .map(move |y| num::range_inclusive(min.x, max.x).map(move |x| Point { x, y }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rng::XorShift;
+
+    #[test]
+    fn test_turning() {
+        for &direction in Direction::ALL.iter() {
+            assert_eq!(direction.turn_left().turn_right(), direction);
+            assert_eq!(direction.opposite().opposite(), direction);
+            assert_eq!(direction.turn_right().turn_right(), direction.opposite());
+        }
+        assert_eq!(Direction::Up.turn_left(), Direction::Left);
+        assert_eq!(Direction::Up.turn_right(), Direction::Right);
+        assert_eq!(Direction::Left.opposite(), Direction::Right);
+    }
+
+    #[test]
+    fn test_neighbours() {
+        let point: Point<i64> = Point::new(2, 3);
+        let neighbours4 = point.neighbours4().collect::<Vec<_>>();
+        assert_eq!(
+            neighbours4,
+            vec![
+                Point::new(2, 2),
+                Point::new(3, 3),
+                Point::new(2, 4),
+                Point::new(1, 3)
+            ]
+        );
+
+        let neighbours8 = point.neighbours8().collect::<Vec<_>>();
+        assert_eq!(neighbours8.len(), 8);
+        assert!(neighbours8.iter().all(|&p| p != point));
+        assert!(neighbours8
+            .iter()
+            .all(|&p| (p.x - point.x).abs() <= 1 && (p.y - point.y).abs() <= 1));
+    }
+
+    #[test]
+    fn test_manhattan_distance() {
+        let a: Point<i32> = Point::new(3, -4);
+        assert_eq!(a.manhattan_length(), 7);
+        assert_eq!(a.manhattan_distance(Point::new(-1, 1)), 9);
+        assert_eq!(Direction::Left * 3 + a, Point::new(0, -4));
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        let points = vec![Point::new(1, 5), Point::new(-2, 3), Point::new(4, 4)];
+        let bounds = BoundingBox::from_points(points).unwrap();
+        assert_eq!(bounds.min, Point::new(-2, 3));
+        assert_eq!(bounds.max, Point::new(4, 5));
+        assert_eq!((bounds.width(), bounds.height()), (7, 3));
+        assert!(bounds.contains(Point::new(0, 4)));
+        assert!(!bounds.contains(Point::new(0, 6)));
+
+        let rows = bounds.rows().map(|row| row.count()).collect::<Vec<usize>>();
+        assert_eq!(rows, vec![7, 7, 7]);
+
+        let column = BoundingBox {
+            min: Point::new(0, 0),
+            max: Point::new(0, 9),
+        };
+        assert_eq!(
+            bounds.intersection(&column),
+            Some(BoundingBox {
+                min: Point::new(0, 3),
+                max: Point::new(0, 5),
+            })
+        );
+        assert_eq!(
+            bounds.intersection(&BoundingBox::from_point(Point::new(5, 5))),
+            None
+        );
+
+        let empty: Vec<Point<i32>> = Vec::new();
+        assert_eq!(BoundingBox::from_points(empty), None);
+    }
+
+    fn clockwise_angle(p: Point<i64>) -> f64 {
+        let (x, y) = (p.x as f64, -p.y as f64);
+        let two_pi = 2.0 * std::f64::consts::PI;
+        (x.atan2(y) + two_pi) % two_pi
+    }
+
+    #[test]
+    fn test_clockwise() {
+        let compass = [
+            (0, -1),
+            (1, -1),
+            (1, 0),
+            (1, 1),
+            (0, 1),
+            (-1, 1),
+            (-1, 0),
+            (-1, -1),
+        ]
+        .iter()
+        .map(|&(x, y)| Clockwise(Point::new(x, y)))
+        .collect::<Vec<Clockwise<i32>>>();
+        for (i, a) in compass.iter().enumerate() {
+            for (j, b) in compass.iter().enumerate() {
+                assert_eq!(a.cmp(b), i.cmp(&j));
+            }
+        }
+
+        let origin = Clockwise(Point::new(0, 0));
+        assert!(compass.iter().all(|&direction| origin < direction));
+        assert_eq!(Clockwise(Point::new(2, -3)), Clockwise(Point::new(4, -6)));
+        assert_ne!(Clockwise(Point::new(2, -3)), Clockwise(Point::new(-2, 3)));
+
+        let extreme = Clockwise(Point::new(i64::MAX, i64::MIN + 1));
+        assert_eq!(extreme, Clockwise(Point::new(1, -1)));
+        assert!(extreme < Clockwise(Point::new(i64::MAX, 1 - i64::MAX)));
+        let corner = Clockwise(Point::new(i64::MIN, i64::MIN));
+        assert_eq!(corner, Clockwise(Point::new(-1, -1)));
+        assert!(Clockwise(Point::new(i64::MIN, 0)) < corner);
+    }
+
+    #[test]
+    fn test_clockwise_matches_atan2() {
+        let mut rng = XorShift(0xdead_beef_cafe_f00d);
+        let limit = 1_000_000_000;
+        for _ in 0..20_000 {
+            let a = Point::new(rng.between(-limit, limit), rng.between(-limit, limit));
+            let b = Point::new(rng.between(-limit, limit), rng.between(-limit, limit));
+            if a == Point::origin() || b == Point::origin() {
+                continue;
+            }
+
+            let (angle_a, angle_b) = (clockwise_angle(a), clockwise_angle(b));
+            if (angle_a - angle_b).abs() > 1e-6 {
+                assert_eq!(
+                    Clockwise(a).cmp(&Clockwise(b)),
+                    angle_a.partial_cmp(&angle_b).unwrap(),
+                    "{:?} {:?}",
+                    a,
+                    b
+                );
+            }
+
+            // multiples along one ray are equal however far out they are
+            let (k, m) = (rng.between(1, 1 << 20), rng.between(1, 1 << 20));
+            let small = Point::new(rng.between(-2000, 2000), rng.between(-2000, 2000));
+            assert_eq!(Clockwise(small * k), Clockwise(small * m));
+        }
+
+        // just past Down at a distance of 2^55, neighbouring directions round
+        // to the same f64 angle but are still strictly ordered
+        let far = 1 << 55;
+        let mut fan = (0..200)
+            .map(|_| Point::new(-rng.between(1, 1000), far))
+            .collect::<Vec<_>>();
+        fan.sort_by_key(|&p| Clockwise(p));
+        assert!(fan.windows(2).all(|pair| pair[0].x >= pair[1].x));
+        assert!(fan
+            .windows(2)
+            .all(|pair| (pair[0].x > pair[1].x) == (Clockwise(pair[0]) < Clockwise(pair[1]))));
+        assert!(fan.windows(2).any(|pair| {
+            pair[0].x != pair[1].x && clockwise_angle(pair[0]) == clockwise_angle(pair[1])
+        }));
+
+        let mut points = (0..2000)
+            .map(|_| Point::new(rng.between(-limit, limit), rng.between(-limit, limit)))
+            .collect::<Vec<_>>();
+        points.sort_by_key(|&p| Clockwise(p));
+        let angles = points
+            .iter()
+            .map(|&p| clockwise_angle(p))
+            .collect::<Vec<_>>();
+        assert!(angles.windows(2).all(|pair| pair[0] <= pair[1] + 1e-9));
+    }
+}
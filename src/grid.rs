@@ -0,0 +1,432 @@
+use crate::geometry::{BoundingBox, Point};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt::{Display, Write};
+
+type Position = Point<i64>;
+
+enum Storage<T> {
+    Sparse(HashMap<Position, T>),
+    Dense {
+        area: BoundingBox<i64>,
+        cells: Vec<Option<T>>,
+    },
+}
+
+pub struct Grid<T> {
+    storage: Storage<T>,
+    bounds: Option<BoundingBox<i64>>,
+    len: usize,
+}
+
+impl<T> Default for Grid<T> {
+    fn default() -> Self {
+        Grid::sparse()
+    }
+}
+
+impl<T> Grid<T> {
+    pub fn sparse() -> Self {
+        Grid {
+            storage: Storage::Sparse(HashMap::new()),
+            bounds: None,
+            len: 0,
+        }
+    }
+
+    pub fn dense(area: BoundingBox<i64>) -> Self {
+        Grid {
+            storage: Storage::Dense {
+                area,
+                cells: empty_cells(cell_count(&area)),
+            },
+            bounds: None,
+            len: 0,
+        }
+    }
+
+    pub fn parse(input: &str, tile: impl Fn(char) -> Option<T>) -> Result<Grid<T>, Box<dyn Error>> {
+        let height = input.lines().count();
+        let width = input.lines().map(|line| line.chars().count()).max();
+        let mut grid = match width {
+            Some(width) if width > 0 => Grid::dense(BoundingBox {
+                min: Point::origin(),
+                max: Point::new(i64::try_from(width)? - 1, i64::try_from(height)? - 1),
+            }),
+            _ => Grid::sparse(),
+        };
+
+        for (y, line) in input.lines().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                if let Some(value) = tile(c) {
+                    grid.insert(Point::new(i64::try_from(x)?, i64::try_from(y)?), value);
+                }
+            }
+        }
+
+        Ok(grid)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn bounds(&self) -> Option<BoundingBox<i64>> {
+        self.bounds
+    }
+
+    pub fn get(&self, position: Position) -> Option<&T> {
+        match &self.storage {
+            Storage::Sparse(tiles) => tiles.get(&position),
+            Storage::Dense { area, cells } => {
+                cell_index(area, position).and_then(|i| cells[i].as_ref())
+            }
+        }
+    }
+
+    pub fn contains(&self, position: Position) -> bool {
+        self.get(position).is_some()
+    }
+
+    pub fn insert(&mut self, position: Position, value: T) -> Option<T> {
+        let previous = match &mut self.storage {
+            Storage::Sparse(tiles) => tiles.insert(position, value),
+            Storage::Dense { area, cells } => {
+                if !area.contains(position) {
+                    let new_area = grown(area, position);
+                    let mut new_cells = empty_cells(cell_count(&new_area));
+                    for (i, cell) in cells.iter_mut().enumerate() {
+                        if let Some(i) = cell_index(&new_area, cell_position(area, i)) {
+                            new_cells[i] = cell.take();
+                        }
+                    }
+                    *area = new_area;
+                    *cells = new_cells;
+                }
+                let i = cell_index(area, position).expect("position inside area");
+                cells[i].replace(value)
+            }
+        };
+
+        if previous.is_none() {
+            self.len += 1;
+        }
+        match &mut self.bounds {
+            Some(bounds) => bounds.include(position),
+            None => self.bounds = Some(BoundingBox::from_point(position)),
+        }
+
+        previous
+    }
+
+    pub fn remove(&mut self, position: Position) -> Option<T> {
+        let removed = match &mut self.storage {
+            Storage::Sparse(tiles) => tiles.remove(&position),
+            Storage::Dense { area, cells } => {
+                cell_index(area, position).and_then(|i| cells[i].take())
+            }
+        };
+
+        if removed.is_some() {
+            self.len -= 1;
+            if self.on_boundary(position) {
+                self.recompute_bounds();
+            }
+        }
+        removed
+    }
+
+    pub fn retain(&mut self, mut keep: impl FnMut(Position, &T) -> bool) {
+        let bounds = self.bounds;
+        let mut removed = 0;
+        let mut shrinks = false;
+        let mut keep = |position, value: &T| {
+            let kept = keep(position, value);
+            if !kept {
+                removed += 1;
+                shrinks |= bounds.is_some_and(|bounds| is_on_edge(&bounds, position));
+            }
+            kept
+        };
+
+        match &mut self.storage {
+            Storage::Sparse(tiles) => tiles.retain(|&position, value| keep(position, value)),
+            Storage::Dense { area, cells } => {
+                for (i, cell) in cells.iter_mut().enumerate() {
+                    if let Some(value) = cell {
+                        if !keep(cell_position(area, i), value) {
+                            *cell = None;
+                        }
+                    }
+                }
+            }
+        }
+        self.len -= removed;
+        if shrinks {
+            self.recompute_bounds();
+        }
+    }
+
+    pub fn iter(&self) -> Box<dyn Iterator<Item = (Position, &T)> + '_> {
+        match &self.storage {
+            Storage::Sparse(tiles) => {
+                Box::new(tiles.iter().map(|(&position, value)| (position, value)))
+            }
+            Storage::Dense { area, cells } => {
+                Box::new(cells.iter().enumerate().filter_map(move |(i, cell)| {
+                    cell.as_ref().map(|value| (cell_position(area, i), value))
+                }))
+            }
+        }
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = Position> + '_ {
+        self.iter().map(|(position, _)| position)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &T> + '_ {
+        self.iter().map(|(_, value)| value)
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = Vec<(Position, Option<&T>)>> + '_ {
+        self.bounds
+            .into_iter()
+            .flat_map(|bounds| bounds.rows())
+            .map(move |row| row.map(|position| (position, self.get(position))).collect())
+    }
+
+    pub fn neighbours4(&self, position: Position) -> impl Iterator<Item = (Position, &T)> + '_ {
+        position
+            .neighbours4()
+            .filter_map(move |neighbour| self.get(neighbour).map(|value| (neighbour, value)))
+    }
+
+    pub fn neighbours8(&self, position: Position) -> impl Iterator<Item = (Position, &T)> + '_ {
+        position
+            .neighbours8()
+            .filter_map(move |neighbour| self.get(neighbour).map(|value| (neighbour, value)))
+    }
+
+    pub fn render<G: Display>(&self, glyph: impl Fn(Position, Option<&T>) -> G) -> String {
+        let mut output = String::new();
+        for row in self.rows() {
+            for (position, value) in row {
+                write!(output, "{}", glyph(position, value)).expect("writing to a String");
+            }
+            output.push('\n');
+        }
+        output
+    }
+
+    fn on_boundary(&self, position: Position) -> bool {
+        self.bounds
+            .is_some_and(|bounds| is_on_edge(&bounds, position))
+    }
+
+    fn recompute_bounds(&mut self) {
+        self.bounds = BoundingBox::from_points(self.positions());
+    }
+}
+
+fn is_on_edge(bounds: &BoundingBox<i64>, position: Position) -> bool {
+    position.x == bounds.min.x
+        || position.x == bounds.max.x
+        || position.y == bounds.min.y
+        || position.y == bounds.max.y
+}
+
+// Grows the area to include `position`, at least doubling it along each axis
+// that has to grow so that repeated inserts at the edge stay amortized O(1).
+fn grown(area: &BoundingBox<i64>, position: Position) -> BoundingBox<i64> {
+    let (width, height) = (area.width(), area.height());
+    let mut grown = *area;
+    if position.x < area.min.x {
+        grown.min.x = position.x.min(area.min.x - width);
+    } else if position.x > area.max.x {
+        grown.max.x = position.x.max(area.max.x + width);
+    }
+    if position.y < area.min.y {
+        grown.min.y = position.y.min(area.min.y - height);
+    } else if position.y > area.max.y {
+        grown.max.y = position.y.max(area.max.y + height);
+    }
+    grown
+}
+
+fn cell_count(area: &BoundingBox<i64>) -> usize {
+    usize::try_from(area.width() * area.height()).expect("valid grid area")
+}
+
+fn empty_cells<T>(count: usize) -> Vec<Option<T>> {
+    (0..count).map(|_| None).collect()
+}
+
+fn cell_index(area: &BoundingBox<i64>, position: Position) -> Option<usize> {
+    if area.contains(position) {
+        let offset = position - area.min;
+        usize::try_from(offset.y * area.width() + offset.x).ok()
+    } else {
+        None
+    }
+}
+
+fn cell_position(area: &BoundingBox<i64>, i: usize) -> Position {
+    let i = i as i64;
+    area.min + Point::new(i % area.width(), i / area.width())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn both_backends() -> Vec<Grid<char>> {
+        let area = BoundingBox {
+            min: Point::origin(),
+            max: Point::new(1, 1),
+        };
+        vec![Grid::sparse(), Grid::dense(area)]
+    }
+
+    #[test]
+    fn test_insert_tracks_bounds() {
+        for mut grid in both_backends() {
+            assert_eq!(grid.bounds(), None);
+            assert_eq!(grid.insert(Point::new(1, 1), 'a'), None);
+            assert_eq!(grid.insert(Point::new(-2, 3), 'b'), None);
+            assert_eq!(grid.insert(Point::new(1, 1), 'c'), Some('a'));
+
+            assert_eq!(grid.len(), 2);
+            assert_eq!(grid.get(Point::new(1, 1)), Some(&'c'));
+            assert_eq!(grid.get(Point::new(-2, 3)), Some(&'b'));
+            assert_eq!(grid.get(Point::new(0, 0)), None);
+            assert_eq!(
+                grid.bounds(),
+                Some(BoundingBox {
+                    min: Point::new(-2, 1),
+                    max: Point::new(1, 3),
+                })
+            );
+
+            assert_eq!(grid.remove(Point::new(-2, 3)), Some('b'));
+            assert_eq!(grid.len(), 1);
+            assert_eq!(
+                grid.bounds(),
+                Some(BoundingBox::from_point(Point::new(1, 1)))
+            );
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        for mut grid in both_backends() {
+            grid.insert(Point::new(3, -1), '#');
+            grid.insert(Point::new(1, 0), '#');
+            let rendered = grid.render(|_, tile| if tile.is_some() { '█' } else { ' ' });
+            assert_eq!(rendered, "  █\n█  \n");
+        }
+    }
+
+    #[test]
+    fn test_parse_and_neighbours() {
+        let input = "#.#\n###\n.#.\n";
+        let grid = Grid::parse(input, |c| Some(c).filter(|&c| c == '#')).unwrap();
+
+        assert_eq!(grid.len(), 6);
+        assert_eq!(grid.neighbours4(Point::new(1, 1)).count(), 3);
+        assert_eq!(grid.neighbours8(Point::new(1, 1)).count(), 5);
+
+        let rows = grid
+            .rows()
+            .map(|row| {
+                row.iter()
+                    .map(|(_, tile)| tile.is_some())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rows,
+            vec![
+                vec![true, false, true],
+                vec![true, true, true],
+                vec![false, true, false],
+            ]
+        );
+        assert_eq!(grid.render(|_, tile| tile.copied().unwrap_or('.')), input);
+    }
+
+    #[test]
+    fn test_dense_grows_geometrically() {
+        let mut grid = Grid::dense(BoundingBox::from_point(Point::origin()));
+        let mut reallocations = 0;
+        for x in 0..1000 {
+            let before = match &grid.storage {
+                Storage::Dense { area, .. } => *area,
+                Storage::Sparse(_) => unreachable!(),
+            };
+            grid.insert(Point::new(-x, x / 2), x);
+            if let Storage::Dense { area, .. } = &grid.storage {
+                if *area != before {
+                    reallocations += 1;
+                }
+            }
+        }
+
+        assert!(reallocations <= 20, "{} reallocations", reallocations);
+        assert_eq!(grid.len(), 1000);
+        assert_eq!(grid.get(Point::new(-999, 499)), Some(&999));
+        assert_eq!(
+            grid.bounds(),
+            Some(BoundingBox {
+                min: Point::new(-999, 0),
+                max: Point::new(0, 499),
+            })
+        );
+    }
+
+    #[test]
+    fn test_remove_keeps_bounds_unless_on_edge() {
+        for mut grid in both_backends() {
+            for x in 0..3 {
+                grid.insert(Point::new(x, 0), 'a');
+            }
+            grid.remove(Point::new(1, 0));
+            assert_eq!(
+                grid.bounds(),
+                Some(BoundingBox {
+                    min: Point::origin(),
+                    max: Point::new(2, 0),
+                })
+            );
+
+            grid.retain(|position, _| position.x == 0);
+            assert_eq!(grid.len(), 1);
+            assert_eq!(
+                grid.bounds(),
+                Some(BoundingBox::from_point(Point::origin()))
+            );
+
+            grid.remove(Point::origin());
+            assert!(grid.is_empty());
+            assert_eq!(grid.bounds(), None);
+        }
+    }
+
+    #[test]
+    fn test_retain() {
+        for mut grid in both_backends() {
+            grid.insert(Point::new(0, 0), 'a');
+            grid.insert(Point::new(5, 5), 'b');
+            grid.retain(|_, &tile| tile == 'a');
+            assert_eq!(grid.len(), 1);
+            assert_eq!(
+                grid.bounds(),
+                Some(BoundingBox::from_point(Point::origin()))
+            );
+        }
+    }
+}
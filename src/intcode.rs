@@ -0,0 +1,355 @@
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryFrom;
+use std::error::Error;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Mode {
+    Position,
+    Immediate,
+    Relative,
+}
+
+impl Mode {
+    fn from_digit(digit: i64) -> Result<Mode, Box<dyn Error>> {
+        match digit {
+            0 => Ok(Mode::Position),
+            1 => Ok(Mode::Immediate),
+            2 => Ok(Mode::Relative),
+            _ => Err(format!("illegal mode: {}", digit))?,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Operand {
+    Read(Mode),
+    Write(Mode),
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Role {
+    Read,
+    Write,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Opcode {
+    Add,
+    Multiply,
+    Input,
+    Output,
+    JumpIfTrue,
+    JumpIfFalse,
+    LessThan,
+    Equals,
+    AdjustRelativeBase,
+    Halt,
+}
+
+impl Opcode {
+    fn from_value(value: i64) -> Result<Opcode, Box<dyn Error>> {
+        match value {
+            1 => Ok(Opcode::Add),
+            2 => Ok(Opcode::Multiply),
+            3 => Ok(Opcode::Input),
+            4 => Ok(Opcode::Output),
+            5 => Ok(Opcode::JumpIfTrue),
+            6 => Ok(Opcode::JumpIfFalse),
+            7 => Ok(Opcode::LessThan),
+            8 => Ok(Opcode::Equals),
+            9 => Ok(Opcode::AdjustRelativeBase),
+            99 => Ok(Opcode::Halt),
+            x => Err(format!("illegal opcode: {}", x))?,
+        }
+    }
+
+    fn roles(self) -> &'static [Role] {
+        match self {
+            Opcode::Add | Opcode::Multiply | Opcode::LessThan | Opcode::Equals => {
+                &[Role::Read, Role::Read, Role::Write]
+            }
+            Opcode::JumpIfTrue | Opcode::JumpIfFalse => &[Role::Read, Role::Read],
+            Opcode::Input => &[Role::Write],
+            Opcode::Output | Opcode::AdjustRelativeBase => &[Role::Read],
+            Opcode::Halt => &[],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Instruction {
+    pub opcode: Opcode,
+    pub operands: Vec<Operand>,
+}
+
+impl Instruction {
+    pub fn decode(value: i64) -> Result<Instruction, Box<dyn Error>> {
+        if value < 0 {
+            Err(format!("illegal instruction: {}", value))?
+        }
+
+        let opcode = Opcode::from_value(value % 100)?;
+        let mut modes = value / 100;
+
+        let mut operands = Vec::new();
+        for role in opcode.roles() {
+            let mode = Mode::from_digit(modes % 10)?;
+            modes /= 10;
+
+            let operand = match (role, mode) {
+                (Role::Write, Mode::Immediate) => {
+                    Err(format!("immediate mode write in instruction: {}", value))?
+                }
+                (Role::Write, mode) => Operand::Write(mode),
+                (Role::Read, mode) => Operand::Read(mode),
+            };
+            operands.push(operand);
+        }
+
+        if modes != 0 {
+            Err(format!("trailing mode digits in instruction: {}", value))?
+        }
+
+        Ok(Instruction { opcode, operands })
+    }
+}
+
+enum Parameter {
+    Position(usize),
+    Immediate(i64),
+    Relative(i64),
+}
+impl Parameter {
+    fn new(mode: Mode, value: i64) -> Result<Parameter, Box<dyn Error>> {
+        match mode {
+            Mode::Position => Ok(Parameter::Position(usize::try_from(value)?)),
+            Mode::Immediate => Ok(Parameter::Immediate(value)),
+            Mode::Relative => Ok(Parameter::Relative(value)),
+        }
+    }
+}
+
+pub struct Program {
+    instructions: Vec<i64>,
+    extra_memory: HashMap<usize, i64>,
+    idx: usize,
+    relative_base: i64,
+}
+
+impl Program {
+    pub fn new(instructions: Vec<i64>) -> Self {
+        Program {
+            instructions,
+            extra_memory: HashMap::new(),
+            idx: 0,
+            relative_base: 0,
+        }
+    }
+
+    fn get_value(&mut self, operand: Operand) -> Result<i64, Box<dyn Error>> {
+        let param = match operand {
+            Operand::Read(mode) => Parameter::new(mode, self.instructions[self.idx])?,
+            Operand::Write(_) => Err("expected read operand")?,
+        };
+        self.idx += 1;
+
+        let value = match param {
+            Parameter::Position(i) => {
+                if i < self.instructions.len() {
+                    self.instructions[i]
+                } else {
+                    self.extra_memory.get(&i).copied().unwrap_or(0)
+                }
+            }
+            Parameter::Immediate(value) => value,
+            Parameter::Relative(i) => {
+                let i = (i + self.relative_base) as usize;
+                if i < self.instructions.len() {
+                    self.instructions[i]
+                } else {
+                    self.extra_memory.get(&i).copied().unwrap_or(0)
+                }
+            }
+        };
+
+        Ok(value)
+    }
+
+    fn write_value(&mut self, address: usize, value: i64) {
+        if address < self.instructions.len() {
+            self.instructions[address] = value;
+        } else {
+            let written_value = self.extra_memory.entry(address).or_insert(0);
+            *written_value = value;
+        }
+    }
+
+    fn get_address(&mut self, operand: Operand) -> Result<usize, Box<dyn Error>> {
+        let param = match operand {
+            Operand::Write(mode) => Parameter::new(mode, self.instructions[self.idx])?,
+            Operand::Read(_) => Err("expected write operand")?,
+        };
+        self.idx += 1;
+
+        let address = match param {
+            Parameter::Position(i) => i,
+            Parameter::Immediate(_) => Err("illegal mode for address param")?,
+            Parameter::Relative(i) => usize::try_from(self.relative_base + i)?,
+        };
+        Ok(address)
+    }
+
+    pub fn run_all(&mut self, mut inputs: VecDeque<i64>) -> Result<Vec<i64>, Box<dyn Error>> {
+        let mut outputs = Vec::new();
+        let mut input: Option<i64> = inputs.pop_front();
+        loop {
+            match self.run(input)? {
+                Output::WaitingForInput => {
+                    if inputs.is_empty() {
+                        Err("missing input")?
+                    } else {
+                        input = inputs.pop_front()
+                    }
+                }
+                Output::Halted => return Ok(outputs),
+                Output::Value(value) => outputs.push(value),
+            }
+        }
+    }
+
+    pub fn run(&mut self, mut input: Option<i64>) -> Result<Output, Box<dyn Error>> {
+        while self.idx < self.instructions.len() {
+            let instruction_start_i = self.idx;
+
+            let instruction = Instruction::decode(self.instructions[self.idx])?;
+            let operands = &instruction.operands;
+            self.idx += 1;
+            match instruction.opcode {
+                Opcode::Add => {
+                    let value_1 = self.get_value(operands[0])?;
+                    let value_2 = self.get_value(operands[1])?;
+                    let target = self.get_address(operands[2])?;
+                    self.write_value(target, value_1 + value_2);
+                }
+                Opcode::Multiply => {
+                    let value_1 = self.get_value(operands[0])?;
+                    let value_2 = self.get_value(operands[1])?;
+                    let target = self.get_address(operands[2])?;
+                    self.write_value(target, value_1 * value_2);
+                }
+                Opcode::Input => {
+                    if let Some(input_value) = input.take() {
+                        let target = self.get_address(operands[0])?;
+                        self.write_value(target, input_value);
+                    } else {
+                        self.idx = instruction_start_i;
+                        return Ok(Output::WaitingForInput);
+                    }
+                }
+                Opcode::Output => {
+                    let value_1 = self.get_value(operands[0])?;
+                    return Ok(Output::Value(value_1));
+                }
+                Opcode::JumpIfTrue => {
+                    let value_1 = self.get_value(operands[0])?;
+                    let value_2 = self.get_value(operands[1])?;
+
+                    if value_1 != 0 {
+                        self.idx = usize::try_from(value_2)?;
+                    }
+                }
+                Opcode::JumpIfFalse => {
+                    let value_1 = self.get_value(operands[0])?;
+                    let value_2 = self.get_value(operands[1])?;
+
+                    if value_1 == 0 {
+                        self.idx = usize::try_from(value_2)?;
+                    }
+                }
+                Opcode::LessThan => {
+                    let value_1 = self.get_value(operands[0])?;
+                    let value_2 = self.get_value(operands[1])?;
+                    let target = self.get_address(operands[2])?;
+
+                    if value_1 < value_2 {
+                        self.write_value(target, 1);
+                    } else {
+                        self.write_value(target, 0);
+                    }
+                }
+                Opcode::Equals => {
+                    let value_1 = self.get_value(operands[0])?;
+                    let value_2 = self.get_value(operands[1])?;
+                    let target = self.get_address(operands[2])?;
+
+                    if value_1 == value_2 {
+                        self.write_value(target, 1);
+                    } else {
+                        self.write_value(target, 0);
+                    }
+                }
+                Opcode::AdjustRelativeBase => {
+                    let value_1 = self.get_value(operands[0])?;
+                    self.relative_base += value_1;
+                }
+
+                Opcode::Halt => {
+                    return Ok(Output::Halted);
+                }
+            }
+        }
+
+        Err("unexpected end")?
+    }
+}
+
+#[derive(Debug)]
+pub enum Output {
+    Value(i64),
+    WaitingForInput,
+    Halted,
+}
+impl Output {
+    pub fn get_value(&self) -> Option<i64> {
+        if let Output::Value(value) = self {
+            Some(*value)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        let instruction = Instruction::decode(1002).unwrap();
+        assert_eq!(instruction.opcode, Opcode::Multiply);
+        assert_eq!(
+            instruction.operands,
+            vec![
+                Operand::Read(Mode::Position),
+                Operand::Read(Mode::Immediate),
+                Operand::Write(Mode::Position),
+            ]
+        );
+
+        let instruction = Instruction::decode(203).unwrap();
+        assert_eq!(instruction.operands, vec![Operand::Write(Mode::Relative)]);
+
+        assert_eq!(Instruction::decode(99).unwrap().operands, vec![]);
+    }
+
+    #[test]
+    fn test_decode_rejects_invalid_instructions() {
+        assert!(Instruction::decode(11101).is_err());
+        assert!(Instruction::decode(103).is_err());
+        assert!(Instruction::decode(301).is_err());
+        assert!(Instruction::decode(10104).is_err());
+        assert!(Instruction::decode(199).is_err());
+        assert!(Instruction::decode(42).is_err());
+        assert!(Instruction::decode(-1).is_err());
+    }
+}
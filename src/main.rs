@@ -1,3 +1,5 @@
+use advent_of_code_2019_rust::intcode;
+
 mod day01;
 mod day02;
 mod day03;
@@ -15,6 +17,14 @@ mod day14;
 mod day15;
 mod day16;
 mod day17;
+mod export;
+mod geometry;
+mod grid;
+mod network;
+mod ocr;
+mod sif;
+#[cfg(test)]
+mod test_rng;
 
 fn main() {
     let res = day15::main();
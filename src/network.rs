@@ -0,0 +1,307 @@
+use crate::intcode::{Output, Program};
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::error::Error;
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct Packet {
+    pub x: i64,
+    pub y: i64,
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Action {
+    Continue,
+    Send(usize, Packet),
+    Stop,
+}
+
+pub trait Monitor {
+    fn on_packet(&mut self, packet: Packet) -> Action;
+    fn on_idle(&mut self) -> Action;
+}
+
+struct Machine {
+    program: Program,
+    state: Output,
+    queue: VecDeque<i64>,
+    outbox: Vec<i64>,
+    idle_reads: usize,
+}
+
+impl Machine {
+    fn boot(instructions: Vec<i64>, address: usize) -> Result<Self, Box<dyn Error>> {
+        let mut program = Program::new(instructions);
+        let state = match program.run(None)? {
+            Output::WaitingForInput => program.run(Some(i64::try_from(address)?))?,
+            _ => Err(format!("machine {} did not ask for its address", address))?,
+        };
+
+        Ok(Machine {
+            program,
+            state,
+            queue: VecDeque::new(),
+            outbox: Vec::new(),
+            idle_reads: 0,
+        })
+    }
+
+    fn is_idle(&self) -> bool {
+        match self.state {
+            Output::WaitingForInput => self.queue.is_empty() && self.idle_reads >= IDLE_READS,
+            Output::Halted => true,
+            Output::Value(_) => false,
+        }
+    }
+}
+
+const EMPTY_QUEUE: i64 = -1;
+const IDLE_READS: usize = 2;
+
+pub struct Network<M: Monitor> {
+    machines: Vec<Machine>,
+    monitor_address: usize,
+    monitor: M,
+    stopped: bool,
+}
+
+impl<M: Monitor> Network<M> {
+    pub fn new(
+        instructions: &[i64],
+        size: usize,
+        monitor_address: usize,
+        monitor: M,
+    ) -> Result<Self, Box<dyn Error>> {
+        let machines = (0..size)
+            .map(|address| Machine::boot(instructions.to_vec(), address))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Network {
+            machines,
+            monitor_address,
+            monitor,
+            stopped: false,
+        })
+    }
+
+    pub fn monitor(&self) -> &M {
+        &self.monitor
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.machines.iter().all(Machine::is_idle)
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.machines
+            .iter()
+            .all(|machine| matches!(machine.state, Output::Halted))
+    }
+
+    pub fn run(&mut self) -> Result<(), Box<dyn Error>> {
+        while !self.stopped {
+            self.tick()?;
+        }
+        Ok(())
+    }
+
+    pub fn tick(&mut self) -> Result<(), Box<dyn Error>> {
+        for address in 0..self.machines.len() {
+            if let Some((dest, packet)) = self.step_machine(address)? {
+                self.route(dest, packet)?;
+            }
+            if self.stopped {
+                return Ok(());
+            }
+        }
+
+        if self.is_halted() {
+            Err("every machine halted before the monitor stopped the network")?
+        }
+
+        if self.is_idle() {
+            let action = self.monitor.on_idle();
+            self.apply(action)?;
+        }
+
+        Ok(())
+    }
+
+    fn step_machine(&mut self, address: usize) -> Result<Option<(i64, Packet)>, Box<dyn Error>> {
+        let machine = &mut self.machines[address];
+
+        match machine.state {
+            Output::WaitingForInput => {
+                let input = match machine.queue.pop_front() {
+                    Some(value) => {
+                        machine.idle_reads = 0;
+                        value
+                    }
+                    None => {
+                        machine.idle_reads += 1;
+                        EMPTY_QUEUE
+                    }
+                };
+                machine.state = machine.program.run(Some(input))?;
+            }
+            Output::Value(value) => {
+                machine.idle_reads = 0;
+                machine.outbox.push(value);
+                machine.state = machine.program.run(None)?;
+
+                if let [dest, x, y] = machine.outbox[..] {
+                    machine.outbox.clear();
+                    return Ok(Some((dest, Packet { x, y })));
+                }
+            }
+            Output::Halted => (),
+        }
+
+        Ok(None)
+    }
+
+    fn route(&mut self, dest: i64, packet: Packet) -> Result<(), Box<dyn Error>> {
+        let dest = usize::try_from(dest)?;
+        if dest == self.monitor_address {
+            let action = self.monitor.on_packet(packet);
+            self.apply(action)
+        } else {
+            self.deliver(dest, packet)
+        }
+    }
+
+    fn deliver(&mut self, dest: usize, packet: Packet) -> Result<(), Box<dyn Error>> {
+        let machine = self
+            .machines
+            .get_mut(dest)
+            .ok_or_else(|| format!("unknown address: {}", dest))?;
+
+        machine.queue.push_back(packet.x);
+        machine.queue.push_back(packet.y);
+        machine.idle_reads = 0;
+        Ok(())
+    }
+
+    fn apply(&mut self, action: Action) -> Result<(), Box<dyn Error>> {
+        match action {
+            Action::Continue => Ok(()),
+            Action::Send(dest, packet) => self.deliver(dest, packet),
+            Action::Stop => {
+                self.stopped = true;
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Nat {
+    pub first_packet: Option<Packet>,
+    pub last_packet: Option<Packet>,
+    pub last_sent_y: Option<i64>,
+    pub repeated_y: Option<i64>,
+}
+
+impl Monitor for Nat {
+    fn on_packet(&mut self, packet: Packet) -> Action {
+        self.first_packet.get_or_insert(packet);
+        self.last_packet = Some(packet);
+        Action::Continue
+    }
+
+    fn on_idle(&mut self) -> Action {
+        match self.last_packet {
+            Some(packet) if self.last_sent_y == Some(packet.y) => {
+                self.repeated_y = Some(packet.y);
+                Action::Stop
+            }
+            Some(packet) => {
+                self.last_sent_y = Some(packet.y);
+                Action::Send(0, packet)
+            }
+            // nothing has reached the NAT, so nothing will ever wake the network
+            None => Action::Stop,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Recorder {
+        packets: Vec<Packet>,
+        idle_count: usize,
+    }
+    impl Monitor for Recorder {
+        fn on_packet(&mut self, packet: Packet) -> Action {
+            self.packets.push(packet);
+            Action::Continue
+        }
+        fn on_idle(&mut self) -> Action {
+            self.idle_count += 1;
+            match self.idle_count {
+                1 => Action::Send(0, Packet { x: 5, y: 9 }),
+                _ => Action::Stop,
+            }
+        }
+    }
+
+    // machine 0 sends (1, 0, 7); every machine reports received packets to 255
+    fn relay_instructions() -> Vec<i64> {
+        vec![
+            3, 100, 1005, 100, 11, 104, 1, 104, 0, 104, 7, 3, 101, 1008, 101, -1, 103, 1005, 103,
+            11, 3, 102, 104, 255, 4, 100, 4, 102, 1105, 1, 11,
+        ]
+    }
+
+    #[test]
+    fn test_routing_and_idle_detection() {
+        let mut network = Network::new(&relay_instructions(), 3, 255, Recorder::default()).unwrap();
+        network.run().unwrap();
+
+        let monitor = network.monitor();
+        assert_eq!(
+            monitor.packets,
+            vec![Packet { x: 1, y: 7 }, Packet { x: 0, y: 9 }]
+        );
+        assert_eq!(monitor.idle_count, 2);
+    }
+
+    #[test]
+    fn test_nat_stops_on_repeated_y() {
+        let mut network = Network::new(&relay_instructions(), 2, 255, Nat::default()).unwrap();
+        network.run().unwrap();
+
+        let nat = network.monitor();
+        assert_eq!(nat.first_packet, Some(Packet { x: 1, y: 7 }));
+        assert_eq!(nat.repeated_y, Some(7));
+    }
+
+    #[test]
+    fn test_unknown_address() {
+        let instructions = vec![3, 100, 104, 42, 104, 1, 104, 2, 99];
+        let mut network = Network::new(&instructions, 1, 255, Nat::default()).unwrap();
+        assert!(network.run().is_err());
+    }
+
+    #[test]
+    fn test_all_machines_halted() {
+        let instructions = vec![3, 100, 99];
+        let mut network = Network::new(&instructions, 2, 255, Nat::default()).unwrap();
+        assert!(network.run().is_err());
+        assert!(network.is_halted());
+    }
+
+    #[test]
+    fn test_nat_stops_when_idle_without_packets() {
+        let instructions = vec![3, 100, 1105, 1, 0];
+        let mut network = Network::new(&instructions, 2, 255, Nat::default()).unwrap();
+        network.run().unwrap();
+
+        let nat = network.monitor();
+        assert_eq!(nat.first_packet, None);
+        assert_eq!(nat.repeated_y, None);
+    }
+}
@@ -0,0 +1,247 @@
+use crate::geometry::{BoundingBox, Point};
+use crate::grid::Grid;
+use std::error::Error;
+use std::fmt;
+
+const GLYPH_WIDTH: usize = 4;
+const GLYPH_HEIGHT: usize = 6;
+const GLYPH_PITCH: usize = GLYPH_WIDTH + 1;
+
+const FONT: [(char, [&str; GLYPH_HEIGHT]); 17] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct Glyph {
+    pub index: usize,
+    pub column: usize,
+    pub rows: Vec<String>,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum OcrError {
+    Height(usize),
+    Unrecognized(Vec<Glyph>),
+}
+
+impl fmt::Display for OcrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OcrError::Height(height) => write!(
+                f,
+                "expected {} rows of letters, found {}",
+                GLYPH_HEIGHT, height
+            ),
+            OcrError::Unrecognized(glyphs) => {
+                writeln!(f, "unrecognized glyphs:")?;
+                for glyph in glyphs {
+                    writeln!(f, "letter {} at column {}:", glyph.index, glyph.column)?;
+                    for row in &glyph.rows {
+                        writeln!(f, "  {}", row)?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for OcrError {}
+
+pub fn read(rows: &[Vec<bool>]) -> Result<String, OcrError> {
+    if rows.len() != GLYPH_HEIGHT {
+        return Err(OcrError::Height(rows.len()));
+    }
+
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let pixel = |x: usize, y: usize| rows[y].get(x).copied().unwrap_or(false);
+
+    let mut text = String::new();
+    let mut unrecognized = Vec::new();
+
+    for (index, column) in (0..width).step_by(GLYPH_PITCH).enumerate() {
+        let glyph_rows = (0..GLYPH_HEIGHT)
+            .map(|y| {
+                (column..column + GLYPH_WIDTH)
+                    .map(|x| if pixel(x, y) { '#' } else { '.' })
+                    .collect::<String>()
+            })
+            .collect::<Vec<String>>();
+
+        let spacer_is_blank = (0..GLYPH_HEIGHT).all(|y| !pixel(column + GLYPH_WIDTH, y));
+        let letter = FONT
+            .iter()
+            .find(|(_, pattern)| pattern.iter().zip(&glyph_rows).all(|(a, b)| a == b))
+            .map(|&(letter, _)| letter)
+            .filter(|_| spacer_is_blank);
+
+        match letter {
+            Some(letter) => text.push(letter),
+            None if glyph_rows.iter().all(|row| !row.contains('#')) => (),
+            None => {
+                text.push('?');
+                unrecognized.push(Glyph {
+                    index,
+                    column,
+                    rows: glyph_rows,
+                });
+            }
+        }
+    }
+
+    if unrecognized.is_empty() {
+        Ok(text)
+    } else {
+        Err(OcrError::Unrecognized(unrecognized))
+    }
+}
+
+pub fn read_layer(layer: &[Vec<char>], ink: char) -> Result<String, OcrError> {
+    let rows = layer
+        .iter()
+        .map(|row| row.iter().map(|&c| c == ink).collect())
+        .collect::<Vec<Vec<bool>>>();
+    read(&rows)
+}
+
+// Every glyph has ink in its top row and its last column, but 'I' leaves its
+// first column blank, so letters are aligned from the right edge of the ink.
+pub fn glyph_origin(bounds: BoundingBox<i64>) -> Point<i64> {
+    let (width, pitch) = (GLYPH_WIDTH as i64, GLYPH_PITCH as i64);
+    let last = bounds.max.x - (width - 1);
+    let letters_before = (last - bounds.min.x + pitch - 1).div_euclid(pitch);
+    Point::new(last - letters_before * pitch, bounds.min.y)
+}
+
+// Glyphs are read at a fixed pitch from `origin`, the top left corner of the
+// first letter, since trimmed bounds lose blank leading columns such as in 'I'.
+pub fn read_grid<T>(
+    grid: &Grid<T>,
+    origin: Point<i64>,
+    is_ink: impl Fn(&T) -> bool,
+) -> Result<String, OcrError> {
+    let width = grid
+        .bounds()
+        .map_or(0, |bounds| bounds.max.x - origin.x + 1)
+        .max(0);
+    let rows = (0..GLYPH_HEIGHT as i64)
+        .map(|y| {
+            (0..width)
+                .map(|x| grid.get(origin + Point::new(x, y)).is_some_and(&is_ink))
+                .collect()
+        })
+        .collect::<Vec<Vec<bool>>>();
+    read(&rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(rows: &[&str]) -> Vec<Vec<char>> {
+        rows.iter().map(|row| row.chars().collect()).collect()
+    }
+
+    #[test]
+    fn test_read_layer() {
+        let image = layer(&[
+            "1001011110011001111010010",
+            "1001000010100100001010010",
+            "1111000100100000010010010",
+            "1001001000100000100010010",
+            "1001010000100101000010010",
+            "1001011110011001111001100",
+        ]);
+        assert_eq!(read_layer(&image, '1').unwrap(), "HZCZU");
+    }
+
+    #[test]
+    fn test_read_full_font() {
+        let rows = (0..GLYPH_HEIGHT)
+            .map(|y| {
+                FONT.iter()
+                    .flat_map(|(_, pattern)| pattern[y].chars().chain(Some('.')))
+                    .map(|c| c == '#')
+                    .collect()
+            })
+            .collect::<Vec<Vec<bool>>>();
+        assert_eq!(read(&rows).unwrap(), "ABCEFGHIJKLOPRSUZ");
+    }
+
+    #[test]
+    fn test_read_grid() {
+        let mut grid = Grid::sparse();
+        for (y, row) in FONT[10].1.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                grid.insert(Point::new(x as i64 + 7, y as i64 - 3), c == '#');
+            }
+        }
+        assert_eq!(
+            read_grid(&grid, Point::new(7, -3), |&ink| ink).unwrap(),
+            "L"
+        );
+    }
+
+    #[test]
+    fn test_read_grid_with_leading_blank_column() {
+        let mut grid = Grid::sparse();
+        for (index, &(_, pattern)) in [FONT[7], FONT[10]].iter().enumerate() {
+            for (y, row) in pattern.iter().enumerate() {
+                for (x, c) in row.chars().enumerate() {
+                    if c == '#' {
+                        let x = (index * GLYPH_PITCH + x) as i64;
+                        grid.insert(Point::new(x + 1, y as i64), ());
+                    }
+                }
+            }
+        }
+        assert_eq!(grid.bounds().unwrap().min.x, 2);
+        assert_eq!(glyph_origin(grid.bounds().unwrap()), Point::new(1, 0));
+        assert_eq!(read_grid(&grid, Point::new(1, 0), |_| true).unwrap(), "IL");
+    }
+
+    #[test]
+    fn test_unrecognized_glyph() {
+        let image = layer(&[
+            "#..#..#..",
+            "#..#..#..",
+            "####..#..",
+            "#..#..#..",
+            "#..#.....",
+            "#..#..#..",
+        ]);
+        let error = read_layer(&image, '#').unwrap_err();
+        assert_eq!(
+            error,
+            OcrError::Unrecognized(vec![Glyph {
+                index: 1,
+                column: 5,
+                rows: vec![
+                    ".#..".to_string(),
+                    ".#..".to_string(),
+                    ".#..".to_string(),
+                    ".#..".to_string(),
+                    "....".to_string(),
+                    ".#..".to_string(),
+                ],
+            }])
+        );
+        assert_eq!(read_layer(&image[..5], '#'), Err(OcrError::Height(5)));
+    }
+}
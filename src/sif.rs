@@ -0,0 +1,401 @@
+use crate::export::{Format, Raster, Rgb, Rgba};
+use std::error::Error;
+use std::io::{self, Write};
+
+pub const TRANSPARENT: u8 = 2;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Layer {
+    width: usize,
+    pixels: Vec<u8>,
+}
+
+impl Layer {
+    fn filled(width: usize, height: usize, digit: u8) -> Layer {
+        Layer {
+            width,
+            pixels: vec![digit; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.pixels.len() / self.width
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<u8> {
+        if x < self.width {
+            self.pixels.get(y * self.width + x).copied()
+        } else {
+            None
+        }
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[u8]> {
+        self.pixels.chunks_exact(self.width)
+    }
+
+    pub fn count(&self, digit: u8) -> usize {
+        self.pixels.iter().filter(|&&pixel| pixel == digit).count()
+    }
+
+    pub fn histogram(&self) -> [usize; 10] {
+        let mut histogram = [0; 10];
+        for &pixel in &self.pixels {
+            histogram[usize::from(pixel)] += 1;
+        }
+        histogram
+    }
+
+    pub fn to_chars(&self) -> Vec<Vec<char>> {
+        self.rows()
+            .map(|row| row.iter().map(|&digit| char::from(b'0' + digit)).collect())
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Palette {
+    colours: [Rgba; 10],
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette {
+            colours: [
+                Rgba::opaque(Rgb::BLACK),
+                Rgba::opaque(Rgb::WHITE),
+                Rgba::TRANSPARENT,
+                Rgba(230, 60, 50, 255),
+                Rgba(60, 180, 75, 255),
+                Rgba(0, 120, 255, 255),
+                Rgba(255, 200, 0, 255),
+                Rgba(145, 30, 180, 255),
+                Rgba(70, 240, 240, 255),
+                Rgba(128, 128, 128, 255),
+            ],
+        }
+    }
+}
+
+impl Palette {
+    pub fn new(colours: [Rgba; 10]) -> Palette {
+        Palette { colours }
+    }
+
+    pub fn with(mut self, digit: u8, colour: Rgba) -> Result<Palette, Box<dyn Error>> {
+        let slot = self
+            .colours
+            .get_mut(usize::from(digit))
+            .ok_or_else(|| format!("palette digits run from 0 to 9, found {}", digit))?;
+        *slot = colour;
+        Ok(self)
+    }
+
+    pub fn colour(&self, digit: u8) -> Option<Rgba> {
+        self.colours.get(usize::from(digit)).copied()
+    }
+
+    // Image pixels are checked to be digits when decoded.
+    fn pixel_colour(&self, digit: u8) -> Rgba {
+        self.colours[usize::from(digit)]
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Blend {
+    FirstOpaque,
+    LastWins,
+    Alpha,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RgbaBuffer {
+    width: usize,
+    height: usize,
+    pixels: Vec<Rgba>,
+}
+
+impl RgbaBuffer {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<Rgba> {
+        if x < self.width {
+            self.pixels.get(y * self.width + x).copied()
+        } else {
+            None
+        }
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[Rgba]> {
+        self.pixels.chunks_exact(self.width)
+    }
+
+    pub fn to_raster(&self, background: Rgb) -> Raster {
+        let rows = self
+            .rows()
+            .map(|row| row.iter().map(|pixel| pixel.flatten(background)).collect())
+            .collect::<Vec<Vec<Rgb>>>();
+        Raster::from_rows(&rows, |&colour| colour)
+    }
+
+    pub fn write_ppm(&self, background: Rgb, out: &mut impl Write) -> io::Result<()> {
+        self.to_raster(background).write(Format::Ppm, 1, out)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SifImage {
+    width: usize,
+    height: usize,
+    layers: Vec<Layer>,
+}
+
+impl SifImage {
+    pub fn decode(data: &str, width: usize, height: usize) -> Result<SifImage, Box<dyn Error>> {
+        if width == 0 || height == 0 {
+            Err(format!("invalid image dimensions: {}x{}", width, height))?
+        }
+
+        let digits = data
+            .trim_end()
+            .chars()
+            .enumerate()
+            .map(|(i, c)| {
+                c.to_digit(10)
+                    .map(|digit| digit as u8)
+                    .ok_or_else(|| format!("invalid digit {:?} at position {}", c, i))
+            })
+            .collect::<Result<Vec<u8>, _>>()?;
+
+        let layer_size = width * height;
+        if digits.is_empty() || digits.len() % layer_size != 0 {
+            Err(format!(
+                "{} digits is not a whole number of {}x{} layers",
+                digits.len(),
+                width,
+                height
+            ))?
+        }
+
+        let layers = digits
+            .chunks_exact(layer_size)
+            .map(|pixels| Layer {
+                width,
+                pixels: pixels.to_vec(),
+            })
+            .collect();
+
+        Ok(SifImage {
+            width,
+            height,
+            layers,
+        })
+    }
+
+    pub fn encode(&self) -> String {
+        self.layers
+            .iter()
+            .flat_map(|layer| layer.pixels.iter())
+            .map(|&digit| char::from(b'0' + digit))
+            .collect()
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    pub fn checksum(&self) -> usize {
+        let layer = self
+            .layers
+            .iter()
+            .min_by_key(|layer| layer.count(0))
+            .expect("image has at least one layer");
+        layer.count(1) * layer.count(2)
+    }
+
+    pub fn composite(&self) -> Layer {
+        let mut image = Layer::filled(self.width, self.height, TRANSPARENT);
+        for layer in &self.layers {
+            for (pixel, &value) in image.pixels.iter_mut().zip(&layer.pixels) {
+                if *pixel == TRANSPARENT {
+                    *pixel = value;
+                }
+            }
+        }
+        image
+    }
+
+    pub fn render(&self, palette: &Palette, blend: Blend) -> RgbaBuffer {
+        let mut pixels = vec![Rgba::TRANSPARENT; self.width * self.height];
+        let layers = self.layers.iter().map(|layer| &layer.pixels);
+
+        match blend {
+            Blend::FirstOpaque => {
+                for layer in layers {
+                    for (pixel, &digit) in pixels.iter_mut().zip(layer) {
+                        if pixel.is_transparent() {
+                            *pixel = palette.pixel_colour(digit);
+                        }
+                    }
+                }
+            }
+            Blend::LastWins => {
+                for layer in layers {
+                    for (pixel, &digit) in pixels.iter_mut().zip(layer) {
+                        let colour = palette.pixel_colour(digit);
+                        if !colour.is_transparent() {
+                            *pixel = colour;
+                        }
+                    }
+                }
+            }
+            Blend::Alpha => {
+                // the first layer is in front, so paint from the back
+                for layer in layers.rev() {
+                    for (pixel, &digit) in pixels.iter_mut().zip(layer) {
+                        *pixel = palette.pixel_colour(digit).over(*pixel);
+                    }
+                }
+            }
+        }
+
+        RgbaBuffer {
+            width: self.width,
+            height: self.height,
+            pixels,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        let image = SifImage::decode("123456789012\n", 3, 2).unwrap();
+        assert_eq!(image.layers().len(), 2);
+
+        let rows = image.layers()[1].rows().collect::<Vec<_>>();
+        assert_eq!(rows, vec![&[7, 8, 9][..], &[0, 1, 2][..]]);
+        assert_eq!(image.layers()[0].get(2, 1), Some(6));
+        assert_eq!(image.layers()[0].get(3, 0), None);
+        assert_eq!(image.encode(), "123456789012");
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        assert!(SifImage::decode("12345678901", 3, 2).is_err());
+        assert!(SifImage::decode("12345a789012", 3, 2).is_err());
+        assert!(SifImage::decode("", 3, 2).is_err());
+        assert!(SifImage::decode("123456", 0, 2).is_err());
+    }
+
+    #[test]
+    fn test_checksum() {
+        let image = SifImage::decode("001122000012", 3, 2).unwrap();
+        assert_eq!(
+            image.layers()[1].histogram(),
+            [4, 1, 1, 0, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(image.checksum(), 4);
+    }
+
+    #[test]
+    fn test_composite() {
+        let image = SifImage::decode("0222112222120000", 2, 2).unwrap();
+        let composite = image.composite();
+        assert_eq!(composite.pixels(), &[0, 1, 1, 0]);
+        assert_eq!(composite.to_chars(), vec![vec!['0', '1'], vec!['1', '0']]);
+    }
+
+    #[test]
+    fn test_render_blend_modes() {
+        let image = SifImage::decode("0222112222120000", 2, 2).unwrap();
+        let palette = Palette::default();
+        let (black, white) = (Rgba::opaque(Rgb::BLACK), Rgba::opaque(Rgb::WHITE));
+
+        let first = image.render(&palette, Blend::FirstOpaque);
+        let expected = image
+            .composite()
+            .pixels()
+            .iter()
+            .map(|&digit| palette.colour(digit).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(first.pixels, expected);
+
+        let last = image.render(&palette, Blend::LastWins);
+        assert_eq!(last.pixels, vec![black, black, black, black]);
+
+        let translucent = palette.with(1, Rgba(255, 255, 255, 128)).unwrap();
+        let alpha = image.render(&translucent, Blend::Alpha);
+        assert_eq!(alpha.get(0, 0), Some(black));
+        assert_eq!(alpha.get(1, 0), Some(Rgba(128, 128, 128, 255)));
+        assert_eq!(alpha.get(0, 1), Some(Rgba(128, 128, 128, 255)));
+
+        let opaque = image.render(&palette, Blend::Alpha);
+        assert_eq!(opaque.get(1, 0), Some(white));
+        assert_eq!(opaque.to_raster(Rgb::BLACK), first.to_raster(Rgb::BLACK));
+    }
+
+    #[test]
+    fn test_first_opaque_with_translucent_layer() {
+        let image = SifImage::decode("0222112222120000", 2, 2).unwrap();
+        let translucent = Palette::default()
+            .with(1, Rgba(255, 255, 255, 128))
+            .unwrap();
+
+        let first = image.render(&translucent, Blend::FirstOpaque);
+        assert_eq!(first.get(0, 0), Some(Rgba::opaque(Rgb::BLACK)));
+        assert_eq!(first.get(1, 0), Some(Rgba(255, 255, 255, 128)));
+
+        let alpha = image.render(&translucent, Blend::Alpha);
+        assert_eq!(alpha.get(1, 0), Some(Rgba(128, 128, 128, 255)));
+        assert_ne!(first, alpha);
+    }
+
+    #[test]
+    fn test_palette_digits() {
+        let palette = Palette::default();
+        assert_eq!(palette.colour(9), Some(Rgba(128, 128, 128, 255)));
+        assert_eq!(palette.colour(10), None);
+        assert_eq!(
+            palette.with(10, Rgba::TRANSPARENT).unwrap_err().to_string(),
+            "palette digits run from 0 to 9, found 10"
+        );
+    }
+
+    #[test]
+    fn test_write_ppm() {
+        let image = SifImage::decode("2122", 2, 1).unwrap();
+        let buffer = image.render(&Palette::default(), Blend::FirstOpaque);
+
+        let mut out = Vec::new();
+        buffer.write_ppm(Rgb(0, 0, 255), &mut out).unwrap();
+        let mut expected = b"P6\n2 1\n255\n".to_vec();
+        expected.extend_from_slice(&[0, 0, 255, 255, 255, 255]);
+        assert_eq!(out, expected);
+    }
+}